@@ -1,18 +1,22 @@
 use crate::error::Error;
 use crate::ignore_block::IgnoreBlocks;
-use crate::token::Tokenizer;
+use crate::token::{Token, Tokenizer};
 use regex::Regex;
+use std::collections::HashMap;
 
 pub mod assign;
 pub mod close;
 pub mod glacier;
+pub mod help;
 pub mod nominate;
 pub mod note;
 pub mod ping;
+pub mod preview;
 pub mod prioritize;
 pub mod relabel;
 pub mod second;
 pub mod shortcut;
+pub mod unknown;
 
 #[derive(Debug, PartialEq)]
 pub enum Command<'a> {
@@ -26,6 +30,47 @@ pub enum Command<'a> {
     Shortcut(Result<shortcut::ShortcutCommand, Error<'a>>),
     Close(Result<close::CloseCommand, Error<'a>>),
     Note(Result<note::NoteCommand, Error<'a>>),
+    Help(Result<help::HelpCommand, Error<'a>>),
+    /// `@bot preview <command>`, to show what the command would do.
+    Preview(Result<Box<Command<'a>>, Error<'a>>),
+    /// A mention of the bot at the start of a line which isn't followed by
+    /// any known command.
+    Unknown(unknown::UnknownCommand),
+}
+
+/// The words which start the built-in commands, from which the suggestion for
+/// an [`unknown::UnknownCommand`] is picked.
+const COMMAND_NAMES: &[&str] = &[
+    "label",
+    "labels",
+    "claim",
+    "assign",
+    "release-assignment",
+    "vacation",
+    "note",
+    "ping",
+    "nominate",
+    "beta-nominate",
+    "beta-accept",
+    "beta-approve",
+    "prioritize",
+    "second",
+    "seconded",
+    "glacier",
+    "ready",
+    "review",
+    "reviewer",
+    "author",
+    "blocked",
+    "close",
+    "help",
+    "preview",
+];
+
+/// Returns whether `name` starts a built-in command, so can't be used as the
+/// name of an alias or of a shortcut other than a built-in one.
+pub fn is_command_name(name: &str) -> bool {
+    COMMAND_NAMES.contains(&name)
 }
 
 #[derive(Debug)]
@@ -36,6 +81,11 @@ pub struct Input<'a> {
     /// A pattern for finding the start of a command based on the name of the
     /// configured bots.
     bot_re: Regex,
+    /// Words which are replaced by the command they stand for, e.g. `ptal`
+    /// for `ready`.
+    aliases: HashMap<&'a str, &'a str>,
+    /// The names of the shortcuts defined by the repository.
+    shortcuts: Vec<&'a str>,
 }
 
 fn parse_single_command<'a, T, F, M>(
@@ -71,13 +121,105 @@ impl<'a> Input<'a> {
             parsed: 0,
             ignore: IgnoreBlocks::new(input),
             bot_re,
+            aliases: HashMap::new(),
+            shortcuts: Vec::new(),
         }
     }
 
-    fn parse_command(&mut self) -> Option<Command<'a>> {
-        let tok = Tokenizer::new(&self.all[self.parsed..]);
+    /// Adds aliases, given as `(alias, command)` pairs. `@bot <alias>` is
+    /// parsed as `@bot <command>`, where the command may include arguments
+    /// (e.g. `label +T-lang`). Aliases named like a built-in command are
+    /// ignored, so the built-in command wins.
+    pub fn with_aliases(mut self, aliases: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        self.aliases.extend(
+            aliases
+                .into_iter()
+                .filter(|(alias, _)| !is_command_name(alias)),
+        );
+        self
+    }
+
+    /// Adds the names of shortcuts defined by the repository, which are
+    /// parsed as [`shortcut::ShortcutCommand::Custom`]. The built-in
+    /// shortcuts can be redefined, but shortcuts named like another built-in
+    /// command are ignored.
+    pub fn with_shortcuts(mut self, shortcuts: impl IntoIterator<Item = &'a str>) -> Self {
+        self.shortcuts.extend(shortcuts.into_iter().filter(|name| {
+            !is_command_name(name) || shortcut::ShortcutCommand::builtin(name).is_some()
+        }));
+        self
+    }
+
+    /// Parses the command following a mention of the bot. If no command
+    /// matches and the mention starts a line, this is an unknown command.
+    fn parse_command(&mut self, starts_line: bool) -> Option<Command<'a>> {
+        let mut tok = Tokenizer::new(&self.all[self.parsed..]);
         log::info!("identified potential command");
 
+        if let Ok(Some(Token::Word("preview"))) = tok.peek_token() {
+            let _ = tok.next_token();
+            let parsed = match review_prefix(&tok) {
+                Some(after) => parse_single_command(
+                    assign::AssignCommand::parse_review,
+                    Command::Assign,
+                    &after,
+                ),
+                None => self.parse_expanded(tok.clone()),
+            };
+            let c = match parsed {
+                Some((after, c)) => {
+                    tok = after;
+                    Ok(Box::new(c))
+                }
+                None => Err(tok.clone().error(preview::ParseError::NoCommand)),
+            };
+            let c = Command::Preview(c);
+            // Even a failed preview consumes the rest of the line, so that
+            // nothing in it (like an `r?`) runs for real.
+            if !c.is_ok() {
+                while let Ok(Some(token)) = tok.next_token() {
+                    if token == Token::EndOfLine {
+                        break;
+                    }
+                }
+            }
+            self.parsed += tok.position();
+            return Some(c);
+        }
+
+        let Some((mut tok, c)) = self.parse_expanded(tok.clone()) else {
+            // `@bot r? @user` is a review request, which is parsed on its own
+            // once the mention is skipped.
+            if !starts_line || review_prefix(&tok).is_some() {
+                return None;
+            }
+            return self.parse_unknown(tok);
+        };
+        // if we errored out while parsing the command do not move the input forwards
+        if c.is_ok() {
+            self.parsed += tok.position();
+        }
+        Some(c)
+    }
+
+    /// Parses a command from the start of `tok` like [`Input::parse_tokens`],
+    /// where an alias is replaced by its command. The returned tokenizer is
+    /// past the alias itself.
+    fn parse_expanded(&self, mut tok: Tokenizer<'a>) -> Option<(Tokenizer<'a>, Command<'a>)> {
+        if let Ok(Some(Token::Word(word))) = tok.peek_token() {
+            if let Some(command) = self.aliases.get(word).copied() {
+                log::info!("expanding alias {word:?} to {command:?}");
+                let _ = tok.next_token();
+                let (_, c) = self.parse_tokens(Tokenizer::new(command))?;
+                return Some((tok, c));
+            }
+        }
+        self.parse_tokens(tok)
+    }
+
+    /// Parses a command from the start of `tok`, returning the tokenizer
+    /// after the command.
+    fn parse_tokens(&self, tok: Tokenizer<'a>) -> Option<(Tokenizer<'a>, Command<'a>)> {
         let mut success = vec![];
 
         let original_tokenizer = tok.clone();
@@ -123,7 +265,7 @@ impl<'a> Input<'a> {
             &original_tokenizer,
         ));
         success.extend(parse_single_command(
-            shortcut::ShortcutCommand::parse,
+            |tok| shortcut::ShortcutCommand::parse_with_custom(tok, &self.shortcuts),
             Command::Shortcut,
             &original_tokenizer,
         ));
@@ -132,6 +274,11 @@ impl<'a> Input<'a> {
             Command::Close,
             &original_tokenizer,
         ));
+        success.extend(parse_single_command(
+            help::HelpCommand::parse,
+            Command::Help,
+            &original_tokenizer,
+        ));
 
         if success.len() > 1 {
             panic!(
@@ -141,12 +288,19 @@ impl<'a> Input<'a> {
             );
         }
 
-        let (mut tok, c) = success.pop()?;
-        // if we errored out while parsing the command do not move the input forwards
-        if c.is_ok() {
-            self.parsed += tok.position();
-        }
-        Some(c)
+        success.pop()
+    }
+
+    fn parse_unknown(&self, mut tok: Tokenizer<'a>) -> Option<Command<'a>> {
+        let known: Vec<&str> = COMMAND_NAMES
+            .iter()
+            .copied()
+            .chain(self.aliases.keys().copied())
+            .chain(self.shortcuts.iter().copied())
+            .collect();
+        let res = unknown::UnknownCommand::parse(&mut tok, &known);
+        log::info!("parsed unknown command: {:?}", res);
+        res.ok().flatten().map(Command::Unknown)
     }
 
     /// Parses command for `r?`
@@ -165,6 +319,17 @@ impl<'a> Input<'a> {
     }
 }
 
+/// Returns the tokenizer after an `r?` at the start of `tok`, if there is one.
+fn review_prefix<'a>(tok: &Tokenizer<'a>) -> Option<Tokenizer<'a>> {
+    let mut tok = tok.clone();
+    match (tok.next_token(), tok.next_token()) {
+        (Ok(Some(Token::Word(r))), Ok(Some(Token::Question))) if r.eq_ignore_ascii_case("r") => {
+            Some(tok)
+        }
+        _ => None,
+    }
+}
+
 impl<'a> Iterator for Input<'a> {
     type Item = Command<'a>;
 
@@ -182,12 +347,18 @@ impl<'a> Iterator for Input<'a> {
                 continue;
             }
 
+            let starts_line = self.all[..self.parsed + m.start()]
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .is_empty();
             self.parsed += m.end();
             if caps.name("review").is_some() {
                 if let Some(command) = self.parse_review() {
                     return Some(command);
                 }
-            } else if let Some(command) = self.parse_command() {
+            } else if let Some(command) = self.parse_command(starts_line) {
                 return Some(command);
             }
         }
@@ -207,6 +378,9 @@ impl<'a> Command<'a> {
             Command::Shortcut(r) => r.is_ok(),
             Command::Close(r) => r.is_ok(),
             Command::Note(r) => r.is_ok(),
+            Command::Help(r) => r.is_ok(),
+            Command::Preview(r) => r.as_ref().is_ok_and(|c| c.is_ok()),
+            Command::Unknown(_) => false,
         }
     }
 
@@ -238,6 +412,28 @@ fn code_2() {
     assert!(input.next().is_none());
 }
 
+#[test]
+fn html_comment() {
+    let input = "<!--
+r? @octocat
+-->
+@bot claim";
+    let mut input = Input::new(input, vec!["bot"]);
+    assert!(matches!(input.next(), Some(Command::Assign(Ok(_)))));
+    assert_eq!(input.next(), None);
+}
+
+#[test]
+fn email_reply() {
+    let input = "@bot claim
+
+On Mon, Jan 1, 2024 at 10:00 AM Octocat <octocat@example.com> wrote:
+@bot release-assignment";
+    let mut input = Input::new(input, vec!["bot"]);
+    assert!(matches!(input.next(), Some(Command::Assign(Ok(_)))));
+    assert_eq!(input.next(), None);
+}
+
 #[test]
 fn resumes_after_code() {
     // Handles a command after an ignored block.
@@ -300,6 +496,67 @@ fn multiname() {
     assert!(input.next().is_none());
 }
 
+#[test]
+fn help_command() {
+    let mut input = Input::new("@bot help", vec!["bot"]);
+    assert_eq!(input.next(), Some(Command::Help(Ok(help::HelpCommand))));
+    assert_eq!(input.next(), None);
+}
+
+#[test]
+fn aliases() {
+    let input = "@bot ptal, and @bot lang. @bot nom compiler";
+    let mut input = Input::new(input, vec!["bot"]).with_aliases([
+        ("ptal", "ready"),
+        ("lang", "label +T-lang"),
+        ("nom", "label"),
+    ]);
+    assert_eq!(
+        input.next(),
+        Some(Command::Shortcut(Ok(shortcut::ShortcutCommand::Ready)))
+    );
+    assert!(matches!(input.next(), Some(Command::Relabel(Ok(_)))));
+    // The alias is replaced as a whole, the rest of the input isn't used.
+    assert!(matches!(input.next(), Some(Command::Relabel(Err(_)))));
+    assert_eq!(input.next(), None);
+}
+
+#[test]
+fn custom_shortcuts() {
+    let input = "@bot ptal @bot ready";
+    let mut input = Input::new(input, vec!["bot"]).with_shortcuts(["ptal"]);
+    assert_eq!(
+        input.next(),
+        Some(Command::Shortcut(Ok(shortcut::ShortcutCommand::Custom(
+            "ptal".to_string()
+        ))))
+    );
+    assert_eq!(
+        input.next(),
+        Some(Command::Shortcut(Ok(shortcut::ShortcutCommand::Ready)))
+    );
+    assert_eq!(input.next(), None);
+}
+
+#[test]
+fn builtin_commands_win() {
+    let input = "@bot close\n@bot help\n@bot claim\n@bot ready";
+    let mut input = Input::new(input, vec!["bot"])
+        .with_shortcuts(["close", "help", "ready"])
+        .with_aliases([("claim", "label +T-lang")]);
+    assert_eq!(input.next(), Some(Command::Close(Ok(close::CloseCommand))));
+    assert_eq!(input.next(), Some(Command::Help(Ok(help::HelpCommand))));
+    assert!(matches!(input.next(), Some(Command::Assign(Ok(_)))));
+    // The built-in shortcuts can still be redefined.
+    assert_eq!(
+        input.next(),
+        Some(Command::Shortcut(Ok(shortcut::ShortcutCommand::Custom(
+            "ready".to_string()
+        ))))
+    );
+    assert_eq!(input.next(), None);
+}
+
 #[test]
 fn review_commands() {
     for (input, name) in [
@@ -322,6 +579,18 @@ fn review_commands() {
     }
 }
 
+#[test]
+fn review_after_mention() {
+    let mut input = Input::new("@bot r? @octocat", vec!["bot"]);
+    assert_eq!(
+        input.next(),
+        Some(Command::Assign(Ok(assign::AssignCommand::ReviewName {
+            name: "octocat".to_string()
+        })))
+    );
+    assert_eq!(input.next(), None);
+}
+
 #[test]
 fn review_errors() {
     use std::error::Error;
@@ -347,3 +616,104 @@ fn review_ignored() {
         assert_eq!(input.next(), None);
     }
 }
+
+#[test]
+fn unknown_command() {
+    let input = "Thanks!\n@bot lable +T-lang";
+    let mut input = Input::new(input, vec!["bot"]);
+    assert_eq!(
+        input.next(),
+        Some(Command::Unknown(unknown::UnknownCommand {
+            name: "lable".to_string(),
+            suggestion: Some("label".to_string()),
+        }))
+    );
+    assert_eq!(input.next(), None);
+}
+
+#[test]
+fn unknown_command_only_at_line_start() {
+    let input = "thanks @bot for the help\n  @bot asign @octocat";
+    let mut input = Input::new(input, vec!["bot"]);
+    assert!(matches!(
+        input.next(),
+        Some(Command::Unknown(unknown::UnknownCommand { name, .. })) if name == "asign"
+    ));
+    assert_eq!(input.next(), None);
+}
+
+#[test]
+fn unknown_command_suggests_aliases_and_shortcuts() {
+    let input = "@bot pral\n@bot needs-traige";
+    let mut input = Input::new(input, vec!["bot"])
+        .with_aliases([("ptal", "ready")])
+        .with_shortcuts(["needs-triage"]);
+    let suggestions: Vec<_> = input
+        .by_ref()
+        .map(|command| match command {
+            Command::Unknown(unknown) => unknown.suggestion,
+            command => panic!("unexpected {command:?}"),
+        })
+        .collect();
+    assert_eq!(
+        suggestions,
+        [Some("ptal".to_string()), Some("needs-triage".to_string())]
+    );
+}
+
+#[test]
+fn command_names_are_known() {
+    for name in COMMAND_NAMES {
+        let input = format!("@bot {name}");
+        let mut input = Input::new(&input, vec!["bot"]);
+        let command = input.next();
+        assert!(
+            !matches!(command, None | Some(Command::Unknown(_))),
+            "{name}: {command:?}"
+        );
+    }
+}
+
+#[test]
+fn preview() {
+    let input = "@bot preview label +T-lang. @bot preview ptal\n@bot preview";
+    let mut input = Input::new(input, vec!["bot"]).with_aliases([("ptal", "ready")]);
+    assert!(matches!(
+        input.next(),
+        Some(Command::Preview(Ok(command))) if matches!(*command, Command::Relabel(Ok(_)))
+    ));
+    assert_eq!(
+        input.next(),
+        Some(Command::Preview(Ok(Box::new(Command::Shortcut(Ok(
+            shortcut::ShortcutCommand::Ready
+        ))))))
+    );
+    let command = input.next().unwrap();
+    assert!(command.is_err());
+    assert!(matches!(command, Command::Preview(Err(_))));
+}
+
+#[test]
+fn preview_review() {
+    let mut input = Input::new("@bot preview r? @octocat", vec!["bot"]);
+    assert_eq!(
+        input.next(),
+        Some(Command::Preview(Ok(Box::new(Command::Assign(Ok(
+            assign::AssignCommand::ReviewName {
+                name: "octocat".to_string()
+            }
+        ))))))
+    );
+    assert_eq!(input.next(), None);
+
+    // Nothing in a failed preview runs.
+    let mut input = Input::new("@bot preview nonsense r? @octocat\nr? @ferris", vec!["bot"]);
+    assert!(matches!(input.next(), Some(Command::Preview(Err(_)))));
+    assert_eq!(
+        input.next(),
+        Some(Command::Assign(Ok(assign::AssignCommand::ReviewName {
+            name: "ferris".to_string()
+        })))
+    );
+    assert_eq!(input.next(), None);
+}
@@ -7,6 +7,10 @@
 //! ```text
 //! Command: `@bot claim`, `@bot release-assignment`, or `@bot assign @user`.
 //! ```
+//!
+//! Users can also set themselves on vacation, so that they aren't assigned
+//! PRs, with `@bot vacation until <yyyy-mm-dd>`, and come back early with
+//! `@bot vacation end`.
 
 use crate::error::Error;
 use crate::token::{Token, Tokenizer};
@@ -16,8 +20,17 @@ use std::fmt;
 pub enum AssignCommand {
     Own,
     Release,
-    User { username: String },
-    ReviewName { name: String },
+    User {
+        username: String,
+    },
+    ReviewName {
+        name: String,
+    },
+    /// The date is in the `yyyy-mm-dd` format, but may not exist.
+    Vacation {
+        until: String,
+    },
+    EndVacation,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -25,6 +38,8 @@ pub enum ParseError {
     ExpectedEnd,
     MentionUser,
     NoUser,
+    ExpectedUntil,
+    InvalidDate,
 }
 
 impl std::error::Error for ParseError {}
@@ -35,6 +50,8 @@ impl fmt::Display for ParseError {
             ParseError::MentionUser => write!(f, "user should start with @"),
             ParseError::ExpectedEnd => write!(f, "expected end of command"),
             ParseError::NoUser => write!(f, "specify user to assign to"),
+            ParseError::ExpectedUntil => write!(f, "expected `until <yyyy-mm-dd>` or `end`"),
+            ParseError::InvalidDate => write!(f, "expected a date like `2026-11-30`"),
         }
     }
 }
@@ -73,6 +90,25 @@ impl AssignCommand {
             } else {
                 return Err(toks.error(ParseError::ExpectedEnd));
             }
+        } else if let Some(Token::Word("vacation")) = toks.peek_token()? {
+            toks.next_token()?;
+            match toks.next_token()? {
+                Some(Token::Word("until")) => {}
+                Some(Token::Word("end")) => {
+                    *input = toks;
+                    return Ok(Some(AssignCommand::EndVacation));
+                }
+                _ => return Err(toks.error(ParseError::ExpectedUntil)),
+            }
+            match toks.next_token()? {
+                Some(Token::Word(date)) if is_date(date) => {
+                    *input = toks;
+                    Ok(Some(AssignCommand::Vacation {
+                        until: date.to_owned(),
+                    }))
+                }
+                _ => Err(toks.error(ParseError::InvalidDate)),
+            }
         } else {
             return Ok(None);
         }
@@ -93,6 +129,15 @@ impl AssignCommand {
     }
 }
 
+/// Checks that the word has the `yyyy-mm-dd` format.
+fn is_date(word: &str) -> bool {
+    word.len() == 10
+        && word.bytes().enumerate().all(|(i, b)| match i {
+            4 | 7 => b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,6 +180,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn vacation() {
+        assert_eq!(
+            parse("vacation until 2026-11-30."),
+            Ok(Some(AssignCommand::Vacation {
+                until: "2026-11-30".to_owned()
+            })),
+        );
+        assert_eq!(parse("vacation end"), Ok(Some(AssignCommand::EndVacation)));
+    }
+
+    #[test]
+    fn vacation_errs() {
+        use std::error::Error;
+        for (input, err) in [
+            ("vacation", ParseError::ExpectedUntil),
+            ("vacation 2026-11-30", ParseError::ExpectedUntil),
+            ("vacation until", ParseError::InvalidDate),
+            ("vacation until tomorrow", ParseError::InvalidDate),
+            ("vacation until 2026-1-30", ParseError::InvalidDate),
+        ] {
+            assert_eq!(
+                parse(input).unwrap_err().source().unwrap().downcast_ref(),
+                Some(&err),
+                "failed on {input}"
+            );
+        }
+    }
+
     fn parse_review<'a>(input: &'a str) -> Result<Option<AssignCommand>, Error<'a>> {
         let mut toks = Tokenizer::new(input);
         Ok(AssignCommand::parse_review(&mut toks)?)
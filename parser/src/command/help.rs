@@ -0,0 +1,42 @@
+//! The help command parser.
+//!
+//! This lists the commands which are enabled in the repository.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot help`.
+//! ```
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct HelpCommand;
+
+impl HelpCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("help")) = toks.peek_token()? {
+            toks.next_token()?;
+            *input = toks;
+            Ok(Some(Self))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse(input: &str) -> Result<Option<HelpCommand>, Error<'_>> {
+    let mut toks = Tokenizer::new(input);
+    HelpCommand::parse(&mut toks)
+}
+
+#[test]
+fn test_help() {
+    assert_eq!(parse("help"), Ok(Some(HelpCommand)));
+    assert_eq!(parse("help."), Ok(Some(HelpCommand)));
+    assert_eq!(parse("helpful"), Ok(None));
+    assert_eq!(parse("label help"), Ok(None));
+}
@@ -0,0 +1,27 @@
+//! The preview command parser.
+//!
+//! This wraps another command, which is planned but not run. The plan is
+//! posted as a comment instead.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot preview <command>`, e.g. `@bot preview label +T-lang`.
+//! ```
+
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    NoCommand,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoCommand => write!(f, "expected a command to preview"),
+        }
+    }
+}
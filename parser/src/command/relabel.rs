@@ -22,10 +22,16 @@
 //!  - -<label>
 //!  this can start with a + or -, but then the only supported way of adding it
 //!  is with the previous two variants of this (i.e., ++label and -+label).
+//!  - =<label>
+//!  adds the label, and removes the others with the same prefix (e.g. `=T-lang`
+//!  removes `T-compiler`).
 //!  - <label>
 //!
 //! <label>: \S+
 //! ```
+//!
+//! The label to remove can be a glob pattern (e.g. `-T-*`), which is matched
+//! against the labels of the issue.
 
 use crate::error::Error;
 use crate::token::{Token, Tokenizer};
@@ -40,6 +46,8 @@ pub struct RelabelCommand(pub Vec<LabelDelta>);
 pub enum LabelDelta {
     Add(Label),
     Remove(Label),
+    /// Adds the label, and removes the other labels in its family.
+    Replace(Label),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -50,6 +58,7 @@ pub enum ParseError {
     EmptyLabel,
     ExpectedLabelDelta,
     MisleadingTo,
+    NoFamily,
 }
 
 impl std::error::Error for ParseError {}
@@ -60,6 +69,10 @@ impl fmt::Display for ParseError {
             ParseError::EmptyLabel => write!(f, "empty label"),
             ParseError::ExpectedLabelDelta => write!(f, "a label delta"),
             ParseError::MisleadingTo => write!(f, "forbidden `to`, use `+to`"),
+            ParseError::NoFamily => write!(
+                f,
+                "`=` needs a label with a prefix to replace, like `=T-lang`"
+            ),
         }
     }
 }
@@ -72,6 +85,20 @@ impl Label {
             Ok(Label(input.into()))
         }
     }
+
+    /// Whether the label is a glob pattern, like `T-*`.
+    pub fn is_pattern(&self) -> bool {
+        self.0.contains(['*', '?', '['])
+    }
+
+    /// The prefix of the family of labels this belongs to, including the
+    /// dash, e.g. `T-` for `T-lang`.
+    pub fn family(&self) -> Option<&str> {
+        match self.0.find('-') {
+            Some(0) | None => None,
+            Some(idx) => Some(&self.0[..=idx]),
+        }
+    }
 }
 
 impl std::ops::Deref for Label {
@@ -100,6 +127,12 @@ impl LabelDelta {
             Ok(LabelDelta::Remove(
                 Label::parse(&delta[1..]).map_err(|e| input.error(e))?,
             ))
+        } else if let Some(delta) = delta.strip_prefix('=') {
+            let label = Label::parse(delta).map_err(|e| input.error(e))?;
+            if label.family().is_none() || label.is_pattern() {
+                return Err(input.error(ParseError::NoFamily));
+            }
+            Ok(LabelDelta::Replace(label))
         } else {
             Ok(LabelDelta::Add(
                 Label::parse(delta).map_err(|e| input.error(e))?,
@@ -111,6 +144,7 @@ impl LabelDelta {
         match self {
             LabelDelta::Add(l) => l,
             LabelDelta::Remove(l) => l,
+            LabelDelta::Replace(l) => l,
         }
     }
 }
@@ -273,3 +307,31 @@ fn parse_shorter_command_with_to_colon() {
         ]))
     );
 }
+
+#[test]
+fn parse_patterns() {
+    assert_eq!(
+        parse("label -T-* =A-diagnostics +S-waiting-on-review"),
+        Ok(Some(vec![
+            LabelDelta::Remove(Label("T-*".into())),
+            LabelDelta::Replace(Label("A-diagnostics".into())),
+            LabelDelta::Add(Label("S-waiting-on-review".into())),
+        ]))
+    );
+    assert!(Label("T-*".into()).is_pattern());
+    assert!(!Label("T-lang".into()).is_pattern());
+    assert_eq!(Label("I-lang-nominated".into()).family(), Some("I-"));
+    assert_eq!(Label("bug".into()).family(), None);
+    assert_eq!(Label("-bug".into()).family(), None);
+}
+
+#[test]
+fn parse_replace_without_family() {
+    for input in ["label =bug", "label =T-*"] {
+        assert_eq!(
+            parse(input).unwrap_err().source().unwrap().downcast_ref(),
+            Some(&ParseError::NoFamily),
+            "{input}"
+        );
+    }
+}
@@ -1,6 +1,8 @@
 //! The shortcut command parser.
 //!
 //! This can parse predefined shortcut input, single word commands.
+//! Repositories can define more shortcuts, which are parsed as
+//! [`ShortcutCommand::Custom`].
 //!
 //! The grammar is as follows:
 //!
@@ -10,14 +12,15 @@
 
 use crate::error::Error;
 use crate::token::{Token, Tokenizer};
-use std::collections::HashMap;
 use std::fmt;
 
-#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub enum ShortcutCommand {
     Ready,
     Author,
     Blocked,
+    /// A shortcut defined by the repository.
+    Custom(String),
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -33,25 +36,55 @@ impl fmt::Display for ParseError {
 
 impl ShortcutCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
-        let mut shortcuts = HashMap::new();
-        shortcuts.insert("ready", ShortcutCommand::Ready);
-        shortcuts.insert("review", ShortcutCommand::Ready);
-        shortcuts.insert("reviewer", ShortcutCommand::Ready);
-        shortcuts.insert("author", ShortcutCommand::Author);
-        shortcuts.insert("blocked", ShortcutCommand::Blocked);
+        Self::parse_with_custom(input, &[])
+    }
 
+    /// Parses a shortcut, where the `custom` shortcuts take precedence over
+    /// the built-in ones.
+    pub fn parse_with_custom<'a>(
+        input: &mut Tokenizer<'a>,
+        custom: &[&str],
+    ) -> Result<Option<Self>, Error<'a>> {
         let mut toks = input.clone();
         if let Some(Token::Word(word)) = toks.peek_token()? {
-            if !shortcuts.contains_key(word) {
-                return Ok(None);
+            if custom.contains(&word) {
+                toks.next_token()?;
+                *input = toks;
+                return Ok(Some(ShortcutCommand::Custom(word.to_string())));
             }
+        }
+
+        let mut toks = input.clone();
+        if let Some(Token::Word(word)) = toks.peek_token()? {
+            let Some(command) = Self::builtin(word) else {
+                return Ok(None);
+            };
             toks.next_token()?;
             *input = toks;
-            let command = shortcuts.get(word).unwrap();
-            return Ok(Some(*command));
+            return Ok(Some(command));
         }
         Ok(None)
     }
+
+    /// Returns the built-in shortcut with the given name, if there is one.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "ready" | "review" | "reviewer" => Some(ShortcutCommand::Ready),
+            "author" => Some(ShortcutCommand::Author),
+            "blocked" => Some(ShortcutCommand::Blocked),
+            _ => None,
+        }
+    }
+
+    /// The name the shortcut is configured with in `[shortcut]`.
+    pub fn name(&self) -> &str {
+        match self {
+            ShortcutCommand::Ready => "ready",
+            ShortcutCommand::Author => "author",
+            ShortcutCommand::Blocked => "blocked",
+            ShortcutCommand::Custom(name) => name,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -84,3 +117,19 @@ fn test_4() {
 fn test_5() {
     assert_eq!(parse("blocked"), Ok(Some(ShortcutCommand::Blocked)));
 }
+
+#[test]
+fn test_custom() {
+    let parse =
+        |input| ShortcutCommand::parse_with_custom(&mut Tokenizer::new(input), &["ptal", "review"]);
+    assert_eq!(
+        parse("ptal"),
+        Ok(Some(ShortcutCommand::Custom("ptal".to_string())))
+    );
+    assert_eq!(
+        parse("review"),
+        Ok(Some(ShortcutCommand::Custom("review".to_string())))
+    );
+    assert_eq!(parse("ready"), Ok(Some(ShortcutCommand::Ready)));
+    assert_eq!(parse("ptal2"), Ok(None));
+}
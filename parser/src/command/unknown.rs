@@ -0,0 +1,89 @@
+//! The fallback for a mention of the bot which no command parser accepts.
+//!
+//! This is only tried when the mention starts a line, so that the bot being
+//! mentioned in prose (e.g. "thanks @bot!") isn't reported.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot <word>`, where `<word>` isn't a known command.
+//! ```
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct UnknownCommand {
+    /// The word following the mention of the bot.
+    pub name: String,
+    /// The known command nearest to `name`, if there is one close enough.
+    pub suggestion: Option<String>,
+}
+
+impl UnknownCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>, known: &[&str]) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word(name)) = toks.next_token()? {
+            *input = toks;
+            Ok(Some(UnknownCommand {
+                name: name.to_string(),
+                suggestion: suggestion(name, known).map(str::to_string),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Returns the known command nearest to `name` by edit distance.
+fn suggestion<'k>(name: &str, known: &[&'k str]) -> Option<&'k str> {
+    known
+        .iter()
+        .copied()
+        .map(|known| (strsim::damerau_levenshtein(name, known), known))
+        .filter(|(distance, _)| *distance <= std::cmp::max(name.len(), 3) / 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+impl fmt::Display for UnknownCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown command `{}`", self.name)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn parse(input: &str) -> Option<UnknownCommand> {
+    let mut toks = Tokenizer::new(input);
+    UnknownCommand::parse(&mut toks, &["label", "assign", "claim"]).unwrap()
+}
+
+#[test]
+fn test_suggestion() {
+    assert_eq!(
+        parse("lable +T-lang"),
+        Some(UnknownCommand {
+            name: "lable".to_string(),
+            suggestion: Some("label".to_string()),
+        })
+    );
+    assert_eq!(
+        parse("asign @octocat").unwrap().to_string(),
+        "unknown command `asign` (did you mean `assign`?)"
+    );
+    assert_eq!(
+        parse("thanks!").unwrap().to_string(),
+        "unknown command `thanks`"
+    );
+}
+
+#[test]
+fn test_no_word() {
+    assert_eq!(parse(": label"), None);
+    assert_eq!(parse(""), None);
+}
@@ -1,4 +1,5 @@
 use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
 use std::ops::Range;
 
 #[derive(Debug)]
@@ -38,6 +39,31 @@ impl IgnoreBlocks {
             }
         }
 
+        // HTML comments (e.g. the examples in PR templates) and quoted email
+        // replies are found in the raw text instead, skipping the ones which
+        // start in code.
+        let mut pos = 0;
+        while let Some(start) = s[pos..].find("<!--").map(|i| pos + i) {
+            pos = start + "<!--".len();
+            if in_ranges(&ignore, start) {
+                continue;
+            }
+            // An unclosed comment hides the rest of the text.
+            let end = s[pos..]
+                .find("-->")
+                .map_or(s.len(), |i| pos + i + "-->".len());
+            cover(&mut ignore, start..end);
+            pos = end;
+        }
+        // The line introducing the quote may be wrapped.
+        let email_reply = Regex::new(r"(?m)^On [^\n]+(?:\n[^\n]+)? wrote:[ \t\r]*$").unwrap();
+        if let Some(m) = email_reply
+            .find_iter(s)
+            .find(|m| !in_ranges(&ignore, m.start()))
+        {
+            cover(&mut ignore, m.start()..s.len());
+        }
+
         IgnoreBlocks { ignore }
     }
 
@@ -52,6 +78,17 @@ impl IgnoreBlocks {
     }
 }
 
+fn in_ranges(ranges: &[Range<usize>], pos: usize) -> bool {
+    ranges.iter().any(|range| range.contains(&pos))
+}
+
+/// Adds `range` to the sorted `ranges`, replacing the ones it overlaps.
+fn cover(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    ranges.retain(|r| r.end <= range.start || range.end <= r.start);
+    let idx = ranges.partition_point(|r| r.start < range.start);
+    ranges.insert(idx, range);
+}
+
 #[cfg(test)]
 #[derive(Debug, PartialEq, Eq)]
 enum Ignore<'a> {
@@ -239,3 +276,89 @@ fn cbs_11() {
         ],
     );
 }
+
+#[test]
+fn cbs_12() {
+    assert_eq!(
+        bodies("Thanks!\n<!-- r? @octocat -->\n@bot claim"),
+        [
+            Ignore::No("Thanks!\n"),
+            Ignore::Yes("<!-- r? @octocat -->"),
+            Ignore::No("\n@bot claim")
+        ],
+    );
+}
+
+#[test]
+fn cbs_13() {
+    assert_eq!(
+        bodies(
+            "
+<!--
+If you know who should review this, uncomment:
+r? @octocat
+-->
+
+Fixes `#1` <!-- and -->!
+"
+        ),
+        [
+            Ignore::No("\n"),
+            Ignore::Yes("<!--\nIf you know who should review this, uncomment:\nr? @octocat\n-->"),
+            Ignore::No("\n\nFixes "),
+            Ignore::Yes("`#1`"),
+            Ignore::No(" "),
+            Ignore::Yes("<!-- and -->"),
+            Ignore::No("!\n"),
+        ],
+    );
+}
+
+#[test]
+fn cbs_14() {
+    // Comments which start in code don't count, but unclosed ones hide
+    // everything after them.
+    assert_eq!(
+        bodies("`<!--` r? @octocat <!-- `code` r? @octocat"),
+        [
+            Ignore::Yes("`<!--`"),
+            Ignore::No(" r? @octocat "),
+            Ignore::Yes("<!-- `code` r? @octocat"),
+        ],
+    );
+}
+
+#[test]
+fn cbs_15() {
+    assert_eq!(
+        bodies(
+            "Sounds good.
+
+On Mon, Jan 1, 2024 at 10:00 AM Octocat <
+octocat@example.com> wrote:
+
+> r? @octocat
+r? @octocat
+"
+        ),
+        [
+            Ignore::No("Sounds good.\n\n"),
+            Ignore::Yes(
+                "On Mon, Jan 1, 2024 at 10:00 AM Octocat <\noctocat@example.com> wrote:\n\n\
+                 > r? @octocat\nr? @octocat\n"
+            ),
+        ],
+    );
+}
+
+#[test]
+fn cbs_16() {
+    assert_eq!(
+        bodies("On second thought, r? @octocat\n```\nOn Monday Octocat wrote:\n```\nr? @octocat\n"),
+        [
+            Ignore::No("On second thought, r? @octocat\n"),
+            Ignore::Yes("```\nOn Monday Octocat wrote:\n```"),
+            Ignore::No("\nr? @octocat\n"),
+        ],
+    );
+}
@@ -0,0 +1,34 @@
+//! Checks a local `triagebot.toml` and shows what it would do.
+//!
+//! Usage: `check-config [--config <path>] [<changed file>...]`
+//!
+//! This prints the features the configuration enables. Given the files a PR
+//! changes (e.g. from `git diff --name-only master`), it also shows which
+//! reviewers `assign.owners` would pick from, which labels `autolabel` would
+//! add, and which `mentions` entries would ping someone. The org-wide base
+//! configuration isn't taken into account.
+
+use anyhow::Context as _;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    let mut path = String::from("triagebot.toml");
+    if args.peek().map(|arg| arg.as_str()) == Some("--config") {
+        args.next();
+        path = args
+            .next()
+            .context("Usage: check-config [--config <path>] [<file>...]")?;
+    }
+    let files: Vec<String> = args.collect();
+    let files: Vec<&str> = files.iter().map(|file| file.as_str()).collect();
+
+    let contents = std::fs::read(&path).with_context(|| format!("failed to read {path}"))?;
+    match triagebot::handlers::check_config::check(&contents, &files) {
+        Ok(report) => print!("{report}"),
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,6 @@
+//! Prints the JSON Schema of `triagebot.toml`.
+
+fn main() {
+    let schema = triagebot::config::json_schema();
+    println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+}
@@ -0,0 +1,236 @@
+//! Replays saved GitHub webhook payloads through the handlers, without
+//! sending any request to GitHub or Zulip.
+//!
+//! The event name is the value of the `X-GitHub-Event` header the payload was
+//! delivered with (e.g. `issue_comment`). `DATABASE_URL` should point at a
+//! development database.
+//!
+//! Every request is answered locally: requests which would change anything
+//! (comments, label changes, assignments, GraphQL mutations, Zulip messages,
+//! ...) are printed, and requests which only read are answered with the issue
+//! or pull request from the payload, or with the `triagebot.toml` given with
+//! `--config`. Anything else is reported as not found. Octocrab is pointed at
+//! a local server which answers the same way.
+
+use anyhow::Context as _;
+use bytes::Bytes;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Server};
+use reqwest::{Client, Method, Request, Response, StatusCode};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use triagebot::github::{GithubClient, GithubStandIn};
+use triagebot::{db, handlers::Context, EventName};
+
+/// Answers every request locally, recording the ones which would modify
+/// anything.
+struct Recorder {
+    /// The issue or pull request from the payload being replayed, used to
+    /// answer requests for it and assignment requests.
+    issue: Mutex<Option<serde_json::Value>>,
+    /// The contents of the `triagebot.toml` to use, if one was given.
+    config: Option<Bytes>,
+}
+
+impl Recorder {
+    fn answer(&self, req: &Request) -> Response {
+        let body: Option<serde_json::Value> = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|body| serde_json::from_slice(body).ok());
+        let is_read = if req.url().path() == "/graphql" {
+            let query = body.as_ref().and_then(|body| body["query"].as_str());
+            !query.is_some_and(|q| q.trim_start().starts_with("mutation"))
+        } else {
+            req.method() == Method::GET || req.method() == Method::HEAD
+        };
+        if is_read {
+            return match self.read(req) {
+                Some(body) => response(StatusCode::OK, body),
+                None => {
+                    println!("# not available offline: {} {}", req.method(), req.url());
+                    println!();
+                    response(
+                        StatusCode::NOT_FOUND,
+                        Bytes::from_static(br#"{"message":"Not Found"}"#),
+                    )
+                }
+            };
+        }
+
+        println!("{} {}", req.method(), req.url());
+        if let Some(body) = &body {
+            println!("{}", serde_json::to_string_pretty(body).unwrap());
+        } else if let Some(body) = req.body().and_then(|body| body.as_bytes()) {
+            // E.g. the form-encoded Zulip messages.
+            println!("{}", String::from_utf8_lossy(body));
+        }
+        println!();
+
+        // Posting a comment responds with the new comment, which is used to
+        // edit it later.
+        if req.method() == Method::POST && req.url().path().ends_with("/comments") {
+            let comment = serde_json::json!({
+                "id": 1,
+                "body": body.as_ref().and_then(|body| body["body"].as_str()),
+                "html_url": "",
+                "user": { "login": "rustbot" },
+                "updated_at": chrono::Utc::now(),
+            });
+            return response(StatusCode::OK, Bytes::from(comment.to_string()));
+        }
+
+        // Adding assignees responds with the updated issue, which the caller
+        // checks to see if the assignment worked.
+        if req.method() == Method::POST && req.url().path().ends_with("/assignees") {
+            if let Some(mut issue) = self.issue.lock().unwrap().clone() {
+                let assignees = body
+                    .as_ref()
+                    .and_then(|body| body["assignees"].as_array())
+                    .into_iter()
+                    .flatten()
+                    .map(|login| serde_json::json!({ "login": login }))
+                    .collect();
+                issue["assignees"] = serde_json::Value::Array(assignees);
+                return response(StatusCode::OK, Bytes::from(issue.to_string()));
+            }
+        }
+        response(StatusCode::OK, Bytes::from_static(b"{}"))
+    }
+
+    /// Returns the response body for a request which only reads, if it can
+    /// be answered offline.
+    fn read(&self, req: &Request) -> Option<Bytes> {
+        let url = req.url();
+        if url.host_str() == Some("raw.githubusercontent.com") {
+            // The org-wide base configuration isn't known.
+            let mut segments = url.path_segments()?;
+            let repo = segments.nth(1)?;
+            if repo != ".github" && url.path().ends_with("/triagebot.toml") {
+                return self.config.clone();
+            }
+            return None;
+        }
+        let issue = self.issue.lock().unwrap();
+        let issue = issue.as_ref()?;
+        if issue["url"].as_str() == Some(url.as_str()) {
+            return Some(Bytes::from(issue.to_string()));
+        }
+        None
+    }
+}
+
+impl GithubStandIn for Recorder {
+    fn respond(&self, req: &Request) -> Option<Response> {
+        Some(self.answer(req))
+    }
+}
+
+fn response(status: StatusCode, body: Bytes) -> Response {
+    hyper::http::Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap()
+        .into()
+}
+
+/// Starts a local server which answers octocrab's requests with the
+/// recorder, and returns its address.
+fn serve_octocrab(recorder: Arc<Recorder>) -> anyhow::Result<SocketAddr> {
+    let svc = make_service_fn(move |_conn| {
+        let recorder = recorder.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: hyper::Request<Body>| {
+                let recorder = recorder.clone();
+                async move {
+                    let (parts, body) = req.into_parts();
+                    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+                    let path = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+                    let url = format!("https://api.github.com{path}").parse().unwrap();
+                    let mut req = Request::new(parts.method, url);
+                    *req.body_mut() = Some(body.into());
+                    let resp = recorder.answer(&req);
+                    let status = resp.status();
+                    let body = resp.bytes().await.unwrap_or_default();
+                    Ok::<_, Infallible>(
+                        hyper::Response::builder()
+                            .status(status)
+                            .header("content-type", "application/json")
+                            .body(Body::from(body))
+                            .unwrap(),
+                    )
+                }
+            }))
+        }
+    });
+    let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(svc);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    Ok(addr)
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut config = None;
+    if args.first().map(|arg| arg.as_str()) == Some("--config") && args.len() > 1 {
+        let path = args[1].clone();
+        let contents = std::fs::read(&path).with_context(|| format!("failed to read {}", path))?;
+        config = Some(Bytes::from(contents));
+        args.drain(..2);
+    }
+    if args.len() < 2 {
+        eprintln!("Usage: replay [--config <triagebot.toml>] <event-name> <payload.json>...");
+        std::process::exit(1);
+    }
+    // The Zulip handlers need a token, even though nothing is sent to Zulip.
+    std::env::set_var("ZULIP_API_TOKEN", "replay");
+
+    let recorder = Arc::new(Recorder {
+        issue: Mutex::new(None),
+        config,
+    });
+    let octocrab_addr = serve_octocrab(recorder.clone())?;
+    let ctx = Context {
+        username: String::from("rustbot"),
+        db: db::ClientPool::new(),
+        github: GithubClient::new(Client::new(), String::from("replay"))
+            .with_stand_in(recorder.clone()),
+        octocrab: octocrab::OctocrabBuilder::new()
+            .base_uri(format!("http://{octocrab_addr}"))?
+            .build()
+            .expect("Failed to build octocrab."),
+    };
+
+    for path in &args[1..] {
+        let event = args[0].parse::<EventName>().unwrap();
+        if matches!(event, EventName::Other) {
+            anyhow::bail!("unsupported event `{}`", args[0]);
+        }
+        let payload =
+            std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+        let value: serde_json::Value = serde_json::from_str(&payload)
+            .with_context(|| format!("{} is not valid JSON", path))?;
+        *recorder.issue.lock().unwrap() = value
+            .get("issue")
+            .or_else(|| value.get("pull_request"))
+            .cloned();
+
+        println!("# replaying {} from {}", event, path);
+        println!();
+        match triagebot::webhook(event, payload, &ctx, &mut HashSet::new()).await {
+            Ok(true) => println!("# processed"),
+            Ok(false) => println!("# ignored"),
+            Err(e) => println!("# failed: {:?}", e),
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -1,23 +1,49 @@
 use crate::changelogs::ChangelogFormat;
-use crate::github::{GithubClient, Repository};
+use crate::github::{GithubClient, Reaction, Repository};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use tracing as log;
 
-static CONFIG_FILE_NAME: &str = "triagebot.toml";
+pub(crate) static CONFIG_FILE_NAME: &str = "triagebot.toml";
+/// The repository in each organization which holds the org-wide base
+/// configuration, which the configuration of every repository in the
+/// organization inherits from.
+static BASE_CONFIG_REPO: &str = ".github";
 const REFRESH_EVERY: Duration = Duration::from_secs(2 * 60); // Every two minutes
+/// How long raw.githubusercontent.com may keep serving the previous version
+/// of a branch after a push.
+const RAW_FILES_STALE_FOR: Duration = Duration::from_secs(10 * 60);
 
 lazy_static::lazy_static! {
-    static ref CONFIG_CACHE:
-        RwLock<HashMap<String, (Result<Arc<Config>, ConfigurationError>, Instant)>> =
+    static ref CONFIG_CACHE: RwLock<HashMap<String, CachedConfig>> = RwLock::new(HashMap::new());
+    /// The last commit pushed to the default branch of each base
+    /// configuration repository which changed its `triagebot.toml`, and when
+    /// it was pushed, keyed by the lowercase repository name. See [`reload`].
+    static ref BASE_CONFIG_COMMITS: RwLock<HashMap<String, (String, Instant)>> =
         RwLock::new(HashMap::new());
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+struct CachedConfig {
+    config: Result<Arc<Config>, ConfigurationError>,
+    /// The repository the org-wide base configuration was loaded from, if
+    /// there was one.
+    base: Option<String>,
+    fetch_time: Instant,
+}
+
+/// The configuration of triagebot for a repository, read from
+/// `triagebot.toml` in the root of its default branch. Each section enables
+/// a feature.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "kebab-case")]
+#[schemars(title = "triagebot.toml")]
 pub(crate) struct Config {
+    /// Don't fail on unknown keys, e.g. while migrating to a new version of
+    /// triagebot.
+    #[serde(default)]
+    pub(crate) allow_unknown_keys: bool,
     pub(crate) relabel: Option<RelabelConfig>,
     pub(crate) assign: Option<AssignConfig>,
     pub(crate) ping: Option<PingConfig>,
@@ -34,15 +60,77 @@ pub(crate) struct Config {
     pub(crate) note: Option<NoteConfig>,
     pub(crate) mentions: Option<MentionsConfig>,
     pub(crate) no_merges: Option<NoMergesConfig>,
+    pub(crate) ci_failure: Option<CiFailureConfig>,
+    pub(crate) aliases: Option<AliasesConfig>,
+    pub(crate) reactions: Option<ReactionsConfig>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+impl Config {
+    /// Returns the names of the sections which are set, i.e. the features
+    /// which are enabled.
+    pub(crate) fn enabled_features(&self) -> Vec<&'static str> {
+        // Destructured so that new sections can't be forgotten here.
+        let Config {
+            allow_unknown_keys: _,
+            relabel,
+            assign,
+            ping,
+            nominate,
+            prioritize,
+            major_change,
+            glacier,
+            close,
+            autolabel,
+            notify_zulip,
+            github_releases,
+            review_submitted,
+            shortcut,
+            note,
+            mentions,
+            no_merges,
+            ci_failure,
+            aliases,
+            reactions,
+        } = self;
+        [
+            ("relabel", relabel.is_some()),
+            ("assign", assign.is_some()),
+            ("ping", ping.is_some()),
+            ("nominate", nominate.is_some()),
+            ("prioritize", prioritize.is_some()),
+            ("major-change", major_change.is_some()),
+            ("glacier", glacier.is_some()),
+            ("close", close.is_some()),
+            ("autolabel", autolabel.is_some()),
+            ("notify-zulip", notify_zulip.is_some()),
+            ("github-releases", github_releases.is_some()),
+            ("review-submitted", review_submitted.is_some()),
+            ("shortcut", shortcut.is_some()),
+            ("note", note.is_some()),
+            ("mentions", mentions.is_some()),
+            ("no-merges", no_merges.is_some()),
+            ("ci-failure", ci_failure.is_some()),
+            ("aliases", aliases.is_some()),
+            ("reactions", reactions.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+/// Enables `@rustbot nominate`.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NominateConfig {
-    // team name -> label
+    /// The label to add for each team which can be nominated, keyed by team
+    /// name.
     pub(crate) teams: HashMap<String, String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Enables `@rustbot ping`. The teams which can be pinged, keyed by team
+/// name.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct PingConfig {
     // team name -> message
     // message will have the cc string appended
@@ -64,17 +152,32 @@ impl PingConfig {
 
         None
     }
+
+    /// Returns the names of the teams which can be pinged.
+    pub(crate) fn team_names(&self) -> impl Iterator<Item = &str> {
+        self.teams.keys().map(|team| team.as_str())
+    }
+
+    /// Returns the labels which are added when pinging teams.
+    pub(crate) fn labels(&self) -> impl Iterator<Item = &str> {
+        self.teams.values().filter_map(|cfg| cfg.label.as_deref())
+    }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// A team which can be pinged.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct PingTeamConfig {
+    /// The message to post, followed by a list of the team members.
     pub(crate) message: String,
+    /// Other names the team can be pinged by.
     #[serde(default)]
     pub(crate) alias: HashSet<String>,
+    /// A label to add when the team is pinged.
     pub(crate) label: Option<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Enables `@rustbot claim` and `r?`, and assigns reviewers to new PRs.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct AssignConfig {
     /// If `true`, then posts a warning comment if the PR is opened against a
     /// different branch than the default (usually master or main).
@@ -90,8 +193,16 @@ pub(crate) struct AssignConfig {
     /// usernames, team names, or ad-hoc groups.
     #[serde(default)]
     pub(crate) owners: HashMap<String, Vec<String>>,
+    /// Users who can't be assigned, whether through `r?` or `owners`.
+    /// Users can also set themselves on vacation with `@rustbot vacation`.
     #[serde(default)]
     pub(crate) users_on_vacation: HashSet<String>,
+    /// The number of open PRs a reviewer can be assigned before they are no
+    /// longer picked, whether through `r?` or `owners`. Unlimited if unset.
+    pub(crate) review_capacity: Option<u32>,
+    /// Overrides `review_capacity` for some users.
+    #[serde(default)]
+    pub(crate) user_review_capacity: HashMap<String, u32>,
 }
 
 impl AssignConfig {
@@ -101,9 +212,19 @@ impl AssignConfig {
             .iter()
             .any(|vacationer| name_lower == vacationer.to_lowercase())
     }
+
+    /// The number of open PRs the user can be assigned, if it is limited.
+    pub(crate) fn review_capacity(&self, user: &str) -> Option<u32> {
+        self.user_review_capacity
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(user))
+            .map(|(_, capacity)| *capacity)
+            .or(self.review_capacity)
+    }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Warns about merge commits in PRs.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NoMergesConfig {
     /// No action will be taken on PRs with these labels.
     #[serde(default)]
@@ -119,44 +240,146 @@ pub(crate) struct NoMergesConfig {
     pub(crate) message: Option<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Keeps a comment up to date which lists the failing checks of a PR.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
+pub(crate) struct CiFailureConfig {
+    /// Set this label on the PR when checks fail.
+    pub(crate) failed_label: Option<String>,
+    /// Set this label on the PR (and remove `failed_label`) when checks pass
+    /// again after a failure, e.g. `S-waiting-on-review`.
+    pub(crate) passed_label: Option<String>,
+}
+
+/// Enables `@rustbot note`, which keeps a summary in the issue description.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NoteConfig {
     #[serde(default)]
+    #[schemars(skip)]
     _empty: (),
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Mentions people when a PR changes certain paths, keyed by path relative
+/// to the root of the repository. Directories match every file below them.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct MentionsConfig {
     #[serde(flatten)]
     pub(crate) paths: HashMap<String, MentionsPathConfig>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct MentionsPathConfig {
+    /// The message to post when a PR changes the path, instead of the
+    /// default one.
     pub(crate) message: Option<String>,
+    /// Users or teams to mention.
     #[serde(default)]
     pub(crate) cc: Vec<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Enables `@rustbot label`.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct RelabelConfig {
+    /// Labels anyone can add or remove, as globs like `C-*`. Other labels
+    /// can only be changed by members of the team.
     #[serde(default)]
     pub(crate) allow_unauthenticated: Vec<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Enables shortcuts like `@rustbot ready`, which replace the status label of
+/// a PR. The built-in `ready`, `author` and `blocked` shortcuts can be
+/// redefined, and more can be added, keyed by name.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct ShortcutConfig {
+    #[serde(flatten)]
+    pub(crate) shortcuts: HashMap<String, ShortcutDefinition>,
+}
+
+impl ShortcutConfig {
+    /// Returns the shortcut with the given name, either from the
+    /// configuration or built in.
+    pub(crate) fn get(&self, name: &str) -> Option<ShortcutDefinition> {
+        if let Some(shortcut) = self.shortcuts.get(name) {
+            return Some(shortcut.clone());
+        }
+        let status_labels = ["S-waiting-on-review", "S-waiting-on-author", "S-blocked"];
+        let add = match name {
+            "ready" => status_labels[0],
+            "author" => status_labels[1],
+            "blocked" => status_labels[2],
+            _ => return None,
+        };
+        Some(ShortcutDefinition {
+            add: vec![add.to_string()],
+            remove: status_labels
+                .iter()
+                .filter(|label| **label != add)
+                .map(|label| label.to_string())
+                .collect(),
+            allow_issues: false,
+        })
+    }
+
+    /// Returns the names of the shortcuts defined in the configuration.
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.shortcuts.keys().map(|name| name.as_str())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ShortcutDefinition {
+    /// The labels to add.
+    pub(crate) add: Vec<String>,
+    /// The labels to remove, usually the other status labels.
     #[serde(default)]
-    _empty: (),
+    pub(crate) remove: Vec<String>,
+    /// Whether the shortcut can be used on issues, not only on PRs.
+    #[serde(default)]
+    pub(crate) allow_issues: bool,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Words which can be used in place of commands, keyed by the alias. For
+/// example `ptal = "ready"` makes `@rustbot ptal` work like
+/// `@rustbot ready`, and `lang = "label +T-lang"` adds a label. Aliases
+/// named like a built-in command are ignored.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
+pub(crate) struct AliasesConfig {
+    #[serde(flatten)]
+    pub(crate) aliases: HashMap<String, String>,
+}
+
+/// Acknowledges the `relabel`, `assign`, shortcut and `prioritize` commands
+/// with a reaction to the comment which used them.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ReactionsConfig {
+    /// The reaction to a command which worked.
+    #[serde(default = "ReactionsConfig::success_default")]
+    pub(crate) success: Reaction,
+    /// The reaction to a command which failed.
+    #[serde(default = "ReactionsConfig::failure_default")]
+    pub(crate) failure: Reaction,
+}
+
+impl ReactionsConfig {
+    fn success_default() -> Reaction {
+        Reaction::ThumbsUp
+    }
+    fn failure_default() -> Reaction {
+        Reaction::Confused
+    }
+}
+
+/// Enables `@rustbot prioritize`.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct PrioritizeConfig {
+    /// The label to add to issues which need to be prioritized.
     pub(crate) label: String,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Adds labels automatically, keyed by the label to add.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct AutolabelConfig {
     #[serde(flatten)]
     pub(crate) labels: HashMap<String, AutolabelLabelConfig>,
@@ -174,39 +397,65 @@ impl AutolabelConfig {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct AutolabelLabelConfig {
+    /// Add the label when one of these labels is added.
     #[serde(default)]
     pub(crate) trigger_labels: Vec<String>,
+    /// Don't add the label if the issue has one of these labels, which may
+    /// be globs like `T-*`.
     #[serde(default)]
     pub(crate) exclude_labels: Vec<String>,
+    /// Add the label to PRs which change files with one of these path
+    /// prefixes.
     #[serde(default)]
     pub(crate) trigger_files: Vec<String>,
+    /// Add the label to newly opened PRs.
     #[serde(default)]
     pub(crate) new_pr: bool,
+    /// Add the label to newly opened issues.
     #[serde(default)]
     pub(crate) new_issue: bool,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+impl AutolabelLabelConfig {
+    /// Whether any of the changed files is in one of the `trigger_files`.
+    pub(crate) fn is_triggered_by_files(&self, files: &[&str]) -> bool {
+        self.trigger_files
+            .iter()
+            .any(|f| files.iter().any(|diff_file| diff_file.starts_with(f)))
+    }
+}
+
+/// Notifies Zulip when labels change, keyed by the label.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NotifyZulipConfig {
     #[serde(flatten)]
     pub(crate) labels: HashMap<String, NotifyZulipLabelConfig>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NotifyZulipLabelConfig {
+    /// The ID of the Zulip stream to post to.
     pub(crate) zulip_stream: u64,
+    /// The topic to post to. `{number}` and `{title}` are replaced with
+    /// those of the issue.
     pub(crate) topic: String,
+    /// The message to post when the label is added.
     pub(crate) message_on_add: Option<String>,
+    /// The message to post when the label is removed.
     pub(crate) message_on_remove: Option<String>,
+    /// The message to post when an issue with the label is closed.
     pub(crate) message_on_close: Option<String>,
+    /// The message to post when an issue with the label is reopened.
     pub(crate) message_on_reopen: Option<String>,
+    /// Only notify if the issue also has all of these labels.
     #[serde(default)]
     pub(crate) required_labels: Vec<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Enables major change proposals and `@rustbot second`.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct MajorChangeConfig {
     /// A username (typically a group, e.g. T-lang) to ping on Zulip for newly
     /// opened proposals.
@@ -228,7 +477,9 @@ pub(crate) struct MajorChangeConfig {
     /// This is the label to be added to newly opened proposals, so they can be
     /// discussed in a meeting.
     pub(crate) meeting_label: String,
+    /// The ID of the Zulip stream to open a topic in for each proposal.
     pub(crate) zulip_stream: u64,
+    /// Text to append to the comment posted on newly opened proposals.
     pub(crate) open_extra_text: Option<String>,
 }
 
@@ -241,15 +492,20 @@ impl MajorChangeConfig {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Enables `@rustbot glacier`, which adds ICEs to the glacier repository.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct GlacierConfig {}
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Enables `@rustbot close`.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct CloseConfig {}
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Updates the labels of a PR when an assignee requests changes.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct ReviewSubmittedConfig {
+    /// The labels to remove when an assignee requests changes on a PR.
     pub(crate) review_labels: Vec<String>,
+    /// The label to add when an assignee requests changes on a PR.
     pub(crate) reviewed_label: String,
 }
 
@@ -262,53 +518,373 @@ pub(crate) async fn get(
         config
     } else {
         log::trace!("fetching fresh config for {}", repo.full_name);
-        let res = get_fresh_config(gh, repo).await;
-        CONFIG_CACHE
-            .write()
-            .unwrap()
-            .insert(repo.full_name.to_string(), (res.clone(), Instant::now()));
-        res
+        fetch(gh, repo, &repo.default_branch).await
+    }
+}
+
+/// Replaces the cached configuration of the given repository with the one at
+/// commit `sha`, e.g. after a push to the default branch changed it. The
+/// repositories which inherit from it are evicted from the cache.
+///
+/// raw.githubusercontent.com can keep serving the previous version of a
+/// branch for a few minutes after a push (see [`RAW_FILES_STALE_FOR`]), so
+/// the configuration is fetched at the pushed commit instead.
+pub(crate) async fn reload(
+    gh: &GithubClient,
+    repo: &Repository,
+    sha: &str,
+) -> Result<Arc<Config>, ConfigurationError> {
+    if repo.name() == BASE_CONFIG_REPO {
+        BASE_CONFIG_COMMITS.write().unwrap().insert(
+            repo.full_name.to_lowercase(),
+            (sha.to_string(), Instant::now()),
+        );
     }
+    evict(&repo.full_name);
+    fetch(gh, repo, sha).await
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Fetches the configuration of the repository at `git_ref` and caches it.
+async fn fetch(
+    gh: &GithubClient,
+    repo: &Repository,
+    git_ref: &str,
+) -> Result<Arc<Config>, ConfigurationError> {
+    let (res, base) = match get_fresh_config(gh, repo, git_ref).await {
+        Ok((config, base)) => (Ok(config), base),
+        Err(e) => (Err(e), None),
+    };
+    CONFIG_CACHE.write().unwrap().insert(
+        repo.full_name.to_string(),
+        CachedConfig {
+            config: res.clone(),
+            base,
+            fetch_time: Instant::now(),
+        },
+    );
+    res
+}
+
+/// Keeps a GitHub release for each tag in sync with its changelog entry.
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct GitHubReleasesConfig {
+    /// The format of the changelog.
     pub(crate) format: ChangelogFormat,
+    /// The name of the project, used in the release names.
     pub(crate) project_name: String,
+    /// The path of the changelog in the repository.
     pub(crate) changelog_path: String,
+    /// The branch to read the changelog from.
     pub(crate) changelog_branch: String,
 }
 
 fn get_cached_config(repo: &str) -> Option<Result<Arc<Config>, ConfigurationError>> {
     let cache = CONFIG_CACHE.read().unwrap();
-    cache.get(repo).and_then(|(config, fetch_time)| {
-        if fetch_time.elapsed() < REFRESH_EVERY {
-            Some(config.clone())
+    cache.get(repo).and_then(|cached| {
+        if cached.fetch_time.elapsed() < REFRESH_EVERY {
+            if let Some(base) = &cached.base {
+                log::trace!("cached config for {repo} inherits from {base}");
+            }
+            Some(cached.config.clone())
         } else {
             None
         }
     })
 }
 
+/// Returns the JSON Schema of `triagebot.toml`, for editors and other tools
+/// to check and complete it with.
+pub fn json_schema() -> serde_json::Value {
+    let mut schema =
+        serde_json::to_value(schemars::schema_for!(Config)).expect("the schema is valid JSON");
+    // Each section may also be set to `false` to disable the section of the
+    // org-wide base configuration (see [`merge`]).
+    if let Some(properties) = schema["properties"].as_object_mut() {
+        for section in properties.values_mut() {
+            if section["type"] == "boolean" {
+                continue;
+            }
+            let description = section
+                .as_object_mut()
+                .and_then(|section| section.remove("description"));
+            let mut disableable = serde_json::json!({
+                "anyOf": [section.take(), { "const": false }],
+            });
+            if let Some(description) = description {
+                disableable["description"] = description;
+            }
+            *section = disableable;
+        }
+    }
+    schema
+}
+
+/// Removes the cached configuration of the given repository, so that it is
+/// fetched again the next time it is needed. This includes the repositories
+/// which inherit from it, if it holds an org-wide base configuration.
+///
+/// Repository names are compared ignoring case, like GitHub does.
+///
+/// Returns the number of cache entries which were removed.
+pub fn evict(repo: &str) -> usize {
+    let mut cache = CONFIG_CACHE.write().unwrap();
+    let before = cache.len();
+    cache.retain(|name, cached| {
+        !name.eq_ignore_ascii_case(repo)
+            && !cached
+                .base
+                .as_deref()
+                .is_some_and(|base| base.eq_ignore_ascii_case(repo))
+    });
+    let evicted = before - cache.len();
+    log::info!("evicted {evicted} cached configurations for {repo}");
+    evicted
+}
+
+/// Removes the cached configuration of every repository.
+///
+/// Returns the number of cache entries which were removed.
+pub fn evict_all() -> usize {
+    let mut cache = CONFIG_CACHE.write().unwrap();
+    let evicted = cache.len();
+    cache.clear();
+    log::info!("evicted all {evicted} cached configurations");
+    evicted
+}
+
+/// Returns the configuration at `git_ref`, and the repository the org-wide
+/// base configuration was loaded from, if any.
 async fn get_fresh_config(
     gh: &GithubClient,
     repo: &Repository,
-) -> Result<Arc<Config>, ConfigurationError> {
+    git_ref: &str,
+) -> Result<(Arc<Config>, Option<String>), ConfigurationError> {
     let contents = gh
-        .raw_file(&repo.full_name, &repo.default_branch, CONFIG_FILE_NAME)
+        .raw_file(&repo.full_name, git_ref, CONFIG_FILE_NAME)
         .await
         .map_err(|e| ConfigurationError::Http(Arc::new(e)))?
         .ok_or(ConfigurationError::Missing)?;
-    let config = Arc::new(toml::from_slice::<Config>(&contents).map_err(ConfigurationError::Toml)?);
+    let base = get_base_config(gh, repo).await?;
+    let config = Arc::new(parse(
+        base.as_ref().map(|(_, contents)| &contents[..]),
+        &contents,
+    )?);
     log::debug!("fresh configuration for {}: {:?}", repo.full_name, config);
+    Ok((config, base.map(|(base_repo, _)| base_repo)))
+}
+
+/// Fetches the org-wide base configuration for the given repository, if
+/// there is one. Returns the repository it was loaded from and its contents.
+///
+/// The base configuration only applies to repositories which have their own
+/// `triagebot.toml`, so it doesn't enable triagebot on its own.
+pub(crate) async fn get_base_config(
+    gh: &GithubClient,
+    repo: &Repository,
+) -> Result<Option<(String, bytes::Bytes)>, ConfigurationError> {
+    if repo.name() == BASE_CONFIG_REPO {
+        return Ok(None);
+    }
+    let base_repo = format!("{}/{}", repo.owner(), BASE_CONFIG_REPO);
+    // Right after a push which changed the base configuration, use the
+    // pushed commit, since `HEAD` (the default branch) may still be stale.
+    let git_ref = BASE_CONFIG_COMMITS
+        .read()
+        .unwrap()
+        .get(&base_repo.to_lowercase())
+        .filter(|(_, pushed)| pushed.elapsed() < RAW_FILES_STALE_FOR)
+        .map(|(sha, _)| sha.clone())
+        .unwrap_or_else(|| "HEAD".to_string());
+    let contents = gh
+        .raw_file(&base_repo, &git_ref, CONFIG_FILE_NAME)
+        .await
+        .map_err(|e| ConfigurationError::Http(Arc::new(e)))?;
+    Ok(contents.map(|contents| (base_repo, contents)))
+}
+
+/// Parses a `triagebot.toml`, merged over the org-wide base configuration
+/// if there is one (see [`merge`]).
+///
+/// Unknown keys are an error, unless `allow-unknown-keys` is set.
+pub(crate) fn parse(base: Option<&[u8]>, contents: &[u8]) -> Result<Config, ConfigurationError> {
+    let value: toml::Value = toml::from_slice(contents).map_err(ConfigurationError::Toml)?;
+    let base = base
+        .map(toml::from_slice::<toml::Value>)
+        .transpose()
+        .map_err(ConfigurationError::Toml)?;
+    let merged = merge(base, value.clone());
+    // Deserialize the text if nothing was merged, so that errors have line
+    // numbers.
+    let config: Config = if merged == value {
+        toml::from_slice(contents)
+    } else {
+        merged.clone().try_into()
+    }
+    .map_err(ConfigurationError::Toml)?;
+    if !config.allow_unknown_keys {
+        let mut unknown = Vec::new();
+        find_unknown_keys(&merged, &mut Vec::new(), &mut unknown);
+        if !unknown.is_empty() {
+            return Err(ConfigurationError::UnknownKeys(unknown));
+        }
+    }
     Ok(config)
 }
 
+/// Merges a repository's configuration over the org-wide base configuration.
+///
+/// Each section of the repository's configuration replaces the same section
+/// of the base configuration as a whole. Setting a section to `false` (e.g.
+/// `relabel = false` at the top of the file) disables the inherited section.
+fn merge(base: Option<toml::Value>, repo: toml::Value) -> toml::Value {
+    let toml::Value::Table(repo) = repo else {
+        return repo;
+    };
+    let mut merged = match base {
+        Some(toml::Value::Table(base)) => base,
+        _ => toml::value::Table::new(),
+    };
+    for (key, value) in repo {
+        let inherited_is_bool = matches!(merged.get(&key), Some(v) if v.is_bool());
+        if value == toml::Value::Boolean(false) && !inherited_is_bool {
+            merged.remove(&key);
+        } else {
+            merged.insert(key, value);
+        }
+    }
+    toml::Value::Table(merged)
+}
+
+/// A key in `triagebot.toml` which doesn't configure anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// The full path of the key, e.g. `assign.users-on-vacation`.
+    pub path: String,
+    /// The key itself, e.g. `users-on-vacation`.
+    pub key: String,
+    /// The known key with the most similar name, if any are similar enough.
+    pub suggestion: Option<&'static str>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown key `{}`", self.path)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+fn find_unknown_keys(value: &toml::Value, path: &mut Vec<String>, unknown: &mut Vec<UnknownKey>) {
+    let Some(table) = value.as_table() else {
+        return;
+    };
+    let segments: Vec<&str> = path.iter().map(|s| s.as_str()).collect();
+    let known = known_keys(&segments);
+    for (key, value) in table {
+        if let Some(known) = known {
+            if !known.contains(&key.as_str()) {
+                let mut full_path = path.clone();
+                full_path.push(key.clone());
+                unknown.push(UnknownKey {
+                    path: full_path.join("."),
+                    key: key.clone(),
+                    suggestion: suggest(key, known),
+                });
+                continue;
+            }
+        }
+        path.push(key.clone());
+        find_unknown_keys(value, path, unknown);
+        path.pop();
+    }
+}
+
+/// Returns the keys accepted by the table at the given path, or `None` if it
+/// accepts any key (e.g. because the keys are team names or labels).
+///
+/// New sections need to be added here.
+fn known_keys(path: &[&str]) -> Option<&'static [&'static str]> {
+    match path {
+        [] => struct_fields::<Config>(),
+        ["relabel"] => struct_fields::<RelabelConfig>(),
+        ["assign"] => struct_fields::<AssignConfig>(),
+        ["nominate"] => struct_fields::<NominateConfig>(),
+        ["prioritize"] => struct_fields::<PrioritizeConfig>(),
+        ["major-change"] => struct_fields::<MajorChangeConfig>(),
+        ["glacier"] => struct_fields::<GlacierConfig>(),
+        ["close"] => struct_fields::<CloseConfig>(),
+        ["github-releases"] => struct_fields::<GitHubReleasesConfig>(),
+        ["review-submitted"] => struct_fields::<ReviewSubmittedConfig>(),
+        ["note"] => struct_fields::<NoteConfig>(),
+        ["no-merges"] => struct_fields::<NoMergesConfig>(),
+        ["ci-failure"] => struct_fields::<CiFailureConfig>(),
+        ["reactions"] => struct_fields::<ReactionsConfig>(),
+        ["ping", _] => struct_fields::<PingTeamConfig>(),
+        ["autolabel", _] => struct_fields::<AutolabelLabelConfig>(),
+        ["notify-zulip", _] => struct_fields::<NotifyZulipLabelConfig>(),
+        ["mentions", _] => struct_fields::<MentionsPathConfig>(),
+        ["shortcut", _] => struct_fields::<ShortcutDefinition>(),
+        _ => None,
+    }
+}
+
+fn suggest(key: &str, known: &[&'static str]) -> Option<&'static str> {
+    known
+        .iter()
+        .copied()
+        .filter(|known| !known.starts_with('_'))
+        .map(|known| (strsim::damerau_levenshtein(key, known), known))
+        .filter(|(distance, _)| *distance <= std::cmp::max(key.len(), 3) / 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Returns the names of the fields of a struct deriving `Deserialize` (after
+/// renaming), or `None` for other types such as maps.
+fn struct_fields<T: serde::de::DeserializeOwned>() -> Option<&'static [&'static str]> {
+    let mut fields = None;
+    let _ = T::deserialize(FieldsDeserializer(&mut fields));
+    fields
+}
+
+/// A deserializer which only records the fields a struct asks for.
+struct FieldsDeserializer<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de> serde::Deserializer<'de> for FieldsDeserializer<'_> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = Some(fields);
+        Err(serde::de::Error::custom("fields recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum ConfigurationError {
     Missing,
     Toml(toml::de::Error),
+    UnknownKeys(Vec<UnknownKey>),
     Http(Arc<anyhow::Error>),
 }
 
@@ -325,6 +901,17 @@ impl fmt::Display for ConfigurationError {
             ConfigurationError::Toml(e) => {
                 write!(f, "Malformed `triagebot.toml` in default branch.\n{e}")
             }
+            ConfigurationError::UnknownKeys(keys) => {
+                writeln!(f, "Unknown keys in `triagebot.toml` in default branch:")?;
+                for key in keys {
+                    write!(f, "\n- {key}")?;
+                }
+                write!(
+                    f,
+                    "\n\nSet `allow-unknown-keys = true` at the top of `triagebot.toml` \
+                     to ignore them."
+                )
+            }
             ConfigurationError::Http(e) => {
                 write!(
                     f,
@@ -397,6 +984,7 @@ mod tests {
         assert_eq!(
             config,
             Config {
+                allow_unknown_keys: false,
                 relabel: Some(RelabelConfig {
                     allow_unauthenticated: vec!["C-*".into()],
                 }),
@@ -406,13 +994,17 @@ mod tests {
                     adhoc_groups: HashMap::new(),
                     owners: HashMap::new(),
                     users_on_vacation: HashSet::from(["jyn514".into()]),
+                    review_capacity: None,
+                    user_review_capacity: HashMap::new(),
                 }),
                 note: Some(NoteConfig { _empty: () }),
                 ping: Some(PingConfig { teams: ping_teams }),
                 nominate: Some(NominateConfig {
                     teams: nominate_teams
                 }),
-                shortcut: Some(ShortcutConfig { _empty: () }),
+                shortcut: Some(ShortcutConfig {
+                    shortcuts: HashMap::new(),
+                }),
                 prioritize: None,
                 major_change: None,
                 glacier: None,
@@ -423,7 +1015,304 @@ mod tests {
                 review_submitted: None,
                 mentions: None,
                 no_merges: None,
+                ci_failure: None,
+                aliases: None,
+                reactions: None,
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let config = r#"
+            [autolable."T-compiler"]
+            trigger_labels = ["A-diagnostics"]
+
+            [assign]
+            users-on-vacation = ["jyn514"]
+
+            [ping.compiler]
+            message = "Hello"
+            lable = "T-compiler"
+
+            [assign.owners]
+            "/compiler" = ["compiler"]
+        "#;
+        let keys = match parse(None, config.as_bytes()) {
+            Err(ConfigurationError::UnknownKeys(keys)) => keys,
+            other => panic!("expected unknown keys, got {other:?}"),
+        };
+        let keys: Vec<_> = keys.iter().map(|key| key.to_string()).collect();
+        assert_eq!(
+            keys,
+            [
+                "unknown key `assign.users-on-vacation` (did you mean `users_on_vacation`?)",
+                "unknown key `autolable` (did you mean `autolabel`?)",
+                "unknown key `ping.compiler.lable` (did you mean `label`?)",
+            ]
+        );
+
+        let config = format!("allow-unknown-keys = true\n{config}");
+        assert!(parse(None, config.as_bytes()).unwrap().allow_unknown_keys);
+    }
+
+    #[test]
+    fn inherit_base() {
+        let base = r#"
+            [relabel]
+            allow-unauthenticated = ["C-*"]
+
+            [shortcut]
+
+            [prioritize]
+            label = "I-prioritize"
+        "#;
+        let config = r#"
+            shortcut = false
+
+            [relabel]
+            allow-unauthenticated = ["A-*"]
+
+            [note]
+        "#;
+        let config = parse(Some(base.as_bytes()), config.as_bytes()).unwrap();
+        assert_eq!(
+            config.relabel,
+            Some(RelabelConfig {
+                allow_unauthenticated: vec!["A-*".into()],
+            })
+        );
+        assert_eq!(config.shortcut, None);
+        assert_eq!(
+            config.prioritize,
+            Some(PrioritizeConfig {
+                label: "I-prioritize".into(),
+            })
+        );
+        assert_eq!(config.note, Some(NoteConfig { _empty: () }));
+
+        // Unknown keys in the base are reported too.
+        let base = "[relabl]\n";
+        assert!(matches!(
+            parse(Some(base.as_bytes()), b"[note]\n"),
+            Err(ConfigurationError::UnknownKeys(_))
+        ));
+    }
+
+    #[test]
+    fn evict_inheriting_repos() {
+        let cache = |repo: &str, base: Option<&str>| {
+            CONFIG_CACHE.write().unwrap().insert(
+                repo.to_string(),
+                CachedConfig {
+                    config: Err(ConfigurationError::Missing),
+                    base: base.map(|b| b.to_string()),
+                    fetch_time: Instant::now(),
+                },
+            );
+        };
+        let cached = |repo: &str| CONFIG_CACHE.read().unwrap().contains_key(repo);
+        cache("evict-test/a", Some("evict-test/.github"));
+        cache("evict-test/b", Some("evict-test/.github"));
+        cache("evict-test/c", None);
+        cache("evict-test/.github", None);
+
+        assert_eq!(evict("evict-test/a"), 1);
+        assert!(!cached("evict-test/a"));
+        assert!(cached("evict-test/b"));
+
+        assert_eq!(evict("evict-test/.github"), 2);
+        assert!(!cached("evict-test/b"));
+        assert!(!cached("evict-test/.github"));
+        assert!(cached("evict-test/c"));
+        assert_eq!(evict("evict-test/c"), 1);
+
+        cache("Evict-Test/D", Some("Evict-Test/.github"));
+        assert_eq!(evict("evict-test/.GITHUB"), 1);
+        assert!(!cached("Evict-Test/D"));
+    }
+
+    /// Follows `$ref`s, and the `anyOf` schemas which wrap them for optional
+    /// fields.
+    fn resolve<'a>(
+        root: &'a serde_json::Value,
+        schema: &'a serde_json::Value,
+    ) -> &'a serde_json::Value {
+        if let Some(name) = schema["$ref"].as_str() {
+            let name = name.strip_prefix("#/$defs/").unwrap();
+            return resolve(root, &root["$defs"][name]);
+        }
+        if let Some(schemas) = schema["anyOf"].as_array() {
+            if let Some(schema) = schemas.iter().find(|s| s["type"] != "null") {
+                return resolve(root, schema);
+            }
+        }
+        schema
+    }
+
+    /// Returns the schema of the table at the given path.
+    fn table<'a>(schema: &'a serde_json::Value, path: &[&str]) -> &'a serde_json::Value {
+        let mut table = resolve(schema, schema);
+        for key in path {
+            table = match table["properties"].get(key) {
+                Some(field) => resolve(schema, field),
+                // An entry of a map, e.g. a team in `[ping]`.
+                None => resolve(schema, &table["additionalProperties"]),
+            };
+        }
+        table
+    }
+
+    #[test]
+    fn json_schema_matches_deserialize() {
+        let schema = json_schema();
+        let mut paths = vec![vec![]];
+        for key in struct_fields::<Config>().unwrap() {
+            paths.push(vec![*key]);
+            paths.push(vec![*key, "entry"]);
+        }
+        for path in paths {
+            let Some(known) = known_keys(&path) else {
+                continue;
+            };
+            let mut expected: Vec<&str> = known
+                .iter()
+                .copied()
+                .filter(|key| !key.starts_with('_'))
+                .collect();
+            expected.sort();
+            let mut properties: Vec<&str> = table(&schema, &path)["properties"]
+                .as_object()
+                .map(|properties| properties.keys().map(|key| key.as_str()).collect())
+                .unwrap_or_default();
+            properties.sort();
+            assert_eq!(properties, expected, "schema of `{}`", path.join("."));
+        }
+
+        // Every section can be disabled with `false`.
+        for key in struct_fields::<Config>().unwrap() {
+            let section = &schema["properties"][*key];
+            let accepts_false = section["anyOf"]
+                .as_array()
+                .is_some_and(|schemas| schemas.contains(&serde_json::json!({ "const": false })));
+            if *key == "allow-unknown-keys" {
+                assert!(!accepts_false);
+                continue;
+            }
+            assert!(accepts_false, "schema of `{key}` doesn't accept `false`");
+            assert!(
+                parse(None, format!("{key} = false").as_bytes()).is_ok(),
+                "`{key} = false` doesn't deserialize"
+            );
+        }
+
+        let major_change = &table(&schema, &["major-change"])["properties"];
+        assert_eq!(major_change["enabling_label"]["default"], "major-change");
+        assert!(major_change["zulip_stream"]["description"].is_string());
+        let autolabel = &table(&schema, &["autolabel", "A-diagnostics"])["properties"];
+        assert_eq!(autolabel["new_pr"]["default"], false);
+        let format = &table(&schema, &["github-releases"])["properties"]["format"];
+        assert_eq!(resolve(&schema, format)["enum"][0], "rustc");
+    }
+
+    #[test]
+    fn shortcuts() {
+        let config = r#"
+            [shortcut.ready]
+            add = ["waiting-on-review"]
+            remove = ["waiting-on-author"]
+
+            [shortcut.needs-triage]
+            add = ["needs-triage"]
+            allow-issues = true
+
+            [aliases]
+            ptal = "ready"
+        "#;
+        let config = parse(None, config.as_bytes()).unwrap();
+        let shortcut = config.shortcut.unwrap();
+        assert_eq!(shortcut.get("ready").unwrap().add, ["waiting-on-review"]);
+        assert!(shortcut.get("needs-triage").unwrap().allow_issues);
+        // The built-in shortcuts which aren't redefined are kept.
+        assert_eq!(
+            shortcut.get("blocked"),
+            Some(ShortcutDefinition {
+                add: vec!["S-blocked".into()],
+                remove: vec!["S-waiting-on-review".into(), "S-waiting-on-author".into()],
+                allow_issues: false,
+            })
+        );
+        assert_eq!(shortcut.get("review"), None);
+        assert_eq!(config.aliases.unwrap().aliases["ptal"], "ready");
+
+        let unknown = "[shortcut.ready]\nadd = []\nissues = true\n";
+        assert!(matches!(
+            parse(None, unknown.as_bytes()),
+            Err(ConfigurationError::UnknownKeys(_))
+        ));
+    }
+
+    #[test]
+    fn enabled_features() {
+        let config = "[relabel]\n[no-merges]\n[note]\n[ci-failure]\n";
+        let config: Config = toml::from_str(config).unwrap();
+        assert_eq!(
+            config.enabled_features(),
+            ["relabel", "note", "no-merges", "ci-failure"]
+        );
+    }
+
+    #[test]
+    fn known_keys_for_every_section() {
+        // These sections are maps, their entries are checked instead.
+        let maps = [
+            "ping",
+            "autolabel",
+            "notify-zulip",
+            "mentions",
+            "shortcut",
+            "aliases",
+        ];
+        for key in struct_fields::<Config>().unwrap() {
+            if maps.contains(key) || *key == "allow-unknown-keys" {
+                continue;
             }
+            assert!(known_keys(&[*key]).is_some(), "`{key}` is missing");
+        }
+    }
+
+    #[test]
+    fn review_capacity() {
+        let config = parse(
+            None,
+            b"[assign]\nreview_capacity = 10\n\n[assign.user_review_capacity]\nOctocat = 3\n",
+        )
+        .unwrap();
+        let assign = config.assign.unwrap();
+        assert_eq!(assign.review_capacity("octocat"), Some(3));
+        assert_eq!(assign.review_capacity("ferris"), Some(10));
+        let config = parse(None, b"[assign]\n").unwrap();
+        assert_eq!(config.assign.unwrap().review_capacity("ferris"), None);
+    }
+
+    #[test]
+    fn reactions() {
+        let config = parse(None, b"[reactions]\n").unwrap();
+        assert_eq!(
+            config.reactions,
+            Some(ReactionsConfig {
+                success: Reaction::ThumbsUp,
+                failure: Reaction::Confused,
+            })
         );
+        let config = parse(
+            None,
+            b"[reactions]\nsuccess = \"rocket\"\nfailure = \"-1\"\n",
+        )
+        .unwrap();
+        let reactions = config.reactions.unwrap();
+        assert_eq!(reactions.success, Reaction::Rocket);
+        assert_eq!(reactions.failure, Reaction::ThumbsDown);
+        assert!(parse(None, b"[reactions]\nsuccess = \"thumbsup\"\n").is_err());
     }
 }
@@ -4,14 +4,18 @@ use anyhow::Context as _;
 use chrono::Utc;
 use native_tls::{Certificate, TlsConnector};
 use postgres_native_tls::MakeTlsConnector;
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio_postgres::Client as DbClient;
 
+pub mod assigned_prs;
 pub mod issue_data;
 pub mod jobs;
 pub mod notifications;
 pub mod rustc_commits;
+pub mod vacations;
+pub mod webhook_deliveries;
 
 const CERT_URL: &str = "https://s3.amazonaws.com/rds-downloads/rds-ca-2019-root.pem";
 
@@ -68,6 +72,12 @@ impl ClientPool {
     }
 
     pub async fn get(&self) -> PooledClient {
+        self.try_get().await.unwrap()
+    }
+
+    /// Like [`ClientPool::get`], but returns an error instead of panicking if
+    /// a new connection can't be made.
+    pub async fn try_get(&self) -> anyhow::Result<PooledClient> {
         let permit = self.permits.clone().acquire_owned().await.unwrap();
         {
             let mut slots = self.connections.lock().unwrap_or_else(|e| e.into_inner());
@@ -75,20 +85,20 @@ impl ClientPool {
             // "possibly open" connections left).
             while let Some(c) = slots.pop() {
                 if !c.is_closed() {
-                    return PooledClient {
+                    return Ok(PooledClient {
                         client: Some(c),
                         permit,
                         pool: self.connections.clone(),
-                    };
+                    });
                 }
             }
         }
 
-        PooledClient {
-            client: Some(make_client().await.unwrap()),
+        Ok(PooledClient {
+            client: Some(make_client().await?),
             permit,
             pool: self.connections.clone(),
-        }
+        })
     }
 }
 
@@ -183,6 +193,17 @@ pub async fn run_migrations(client: &DbClient) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Returns the number of migrations which have been applied, and the number
+/// of migrations this version of triagebot knows about.
+pub async fn migration_status(client: &DbClient) -> anyhow::Result<(usize, usize)> {
+    let migration_idx: i32 = client
+        .query_one("SELECT migration_counter FROM database_versions", &[])
+        .await
+        .context("getting migration counter")?
+        .get(0);
+    Ok((migration_idx as usize, MIGRATIONS.len()))
+}
+
 pub async fn schedule_jobs(db: &DbClient, jobs: Vec<JobSchedule>) -> anyhow::Result<()> {
     for job in jobs {
         let mut upcoming = job.schedule.upcoming(Utc).take(1);
@@ -208,15 +229,87 @@ pub async fn run_scheduled_jobs(ctx: &Context, db: &DbClient) -> anyhow::Result<
         match handle_job(&ctx, &job.name, &job.metadata).await {
             Ok(_) => {
                 tracing::trace!("job successfully executed (id={})", job.id);
+                crate::metrics::JOB_RUNS
+                    .with_label_values(&[&job.name, "succeeded"])
+                    .inc();
                 delete_job(&db, &job.id).await?;
             }
             Err(e) => {
                 tracing::error!("job failed on execution (id={:?}, error={:?})", job.id, e);
+                crate::metrics::JOB_RUNS
+                    .with_label_values(&[&job.name, "failed"])
+                    .inc();
                 update_job_error_message(&db, &job.id, &e.to_string()).await?;
             }
         }
     }
 
+    crate::metrics::JOB_QUEUE_DEPTH.set(count_pending_jobs(&db).await?);
+
+    Ok(())
+}
+
+/// Processes webhooks from the `webhook_deliveries` inbox until none are
+/// ready.
+///
+/// A retry only runs the handlers which failed on the previous attempts.
+pub async fn process_webhook_deliveries(ctx: &Context, db: &DbClient) -> anyhow::Result<()> {
+    while let Some(mut delivery) = webhook_deliveries::claim_next_delivery(&db).await? {
+        let event = delivery.event.parse().unwrap();
+        let mut finished: HashSet<String> = delivery.finished_handlers.drain(..).collect();
+        let result = {
+            let handling = crate::webhook(event, delivery.payload.clone(), ctx, &mut finished);
+            tokio::pin!(handling);
+            // Keep the claim on the delivery fresh while the handlers run, so
+            // that no other worker picks it up again.
+            let period = webhook_deliveries::HEARTBEAT_INTERVAL;
+            let mut heartbeat =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                tokio::select! {
+                    result = &mut handling => break result,
+                    _ = heartbeat.tick() => {
+                        let res = webhook_deliveries::touch_delivery(db, &delivery.delivery_id).await;
+                        if let Err(e) = res {
+                            tracing::warn!(
+                                "failed to refresh webhook delivery (id={}, error={:?})",
+                                delivery.delivery_id,
+                                e
+                            );
+                        }
+                    }
+                }
+            }
+        };
+        match result {
+            Ok(_) => {
+                tracing::trace!("webhook delivery processed (id={})", delivery.delivery_id);
+                webhook_deliveries::complete_delivery(&db, &delivery.delivery_id).await?;
+            }
+            Err(e) => {
+                let message = format!("{:?}", e);
+                delivery.finished_handlers = finished.into_iter().collect();
+                if webhook_deliveries::fail_delivery(&db, &delivery, &message, e.is_retryable())
+                    .await?
+                {
+                    tracing::error!(
+                        "webhook delivery is dead after {} attempts (id={}, error={})",
+                        delivery.attempts,
+                        delivery.delivery_id,
+                        message
+                    );
+                } else {
+                    tracing::warn!(
+                        "webhook delivery failed, will retry (id={}, attempt={}, error={})",
+                        delivery.delivery_id,
+                        delivery.attempts,
+                        message
+                    );
+                }
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -272,5 +365,50 @@ CREATE UNIQUE INDEX jobs_name_scheduled_at_unique_index
     ON jobs (
         name, scheduled_at
     );
+",
+    "
+CREATE TABLE webhook_deliveries (
+    delivery_id TEXT PRIMARY KEY,
+    event TEXT NOT NULL,
+    status TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    received_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+",
+    "
+ALTER TABLE webhook_deliveries
+    ADD COLUMN payload TEXT,
+    ADD COLUMN next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    ADD COLUMN error_message TEXT;
+",
+    // Deliveries recorded before the inbox existed don't have a payload, so
+    // they can't be retried.
+    "UPDATE webhook_deliveries SET status = 'dead' WHERE status <> 'succeeded';",
+    "CREATE INDEX webhook_deliveries_status_index ON webhook_deliveries (status, next_attempt_at);",
+    "
+CREATE TABLE assigned_prs (
+    repo TEXT NOT NULL,
+    pr_number INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    PRIMARY KEY (repo, pr_number, username)
+);
+",
+    "CREATE INDEX assigned_prs_repo_username_index ON assigned_prs (repo, username);",
+    "
+CREATE TABLE vacations (
+    username TEXT PRIMARY KEY,
+    until DATE NOT NULL
+);
+",
+    "
+ALTER TABLE webhook_deliveries
+    ADD COLUMN finished_handlers TEXT[] NOT NULL DEFAULT '{}';
+",
+    "
+CREATE TABLE assigned_prs_backfills (
+    repo TEXT PRIMARY KEY,
+    backfilled_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
 ",
 ];
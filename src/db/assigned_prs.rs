@@ -0,0 +1,105 @@
+//! The `assigned_prs` table caches who is assigned to each open PR, so that
+//! the workload of reviewers can be taken into account when picking one (see
+//! `handlers::assign`).
+//!
+//! It is kept up to date by `handlers::pr_tracking` from the webhooks of
+//! repositories with an `[assign]` table. The first time the workloads of a
+//! repository are needed, the assignees of all of its open PRs are fetched
+//! from GitHub (see [`backfill`]), which is recorded in
+//! `assigned_prs_backfills`, so that PRs which had no event since the
+//! repository started being tracked are counted as well.
+
+use anyhow::{Context as _, Result};
+use std::collections::HashMap;
+use tokio_postgres::Client as DbClient;
+
+/// Replaces the recorded assignees of a PR. Closed PRs have none.
+pub async fn set_assignees(
+    db: &mut DbClient,
+    repo: &str,
+    pr_number: u64,
+    assignees: &[&str],
+) -> Result<()> {
+    let pr_number = pr_number as i32;
+    let transaction = db.transaction().await?;
+    transaction
+        .execute(
+            "DELETE FROM assigned_prs WHERE repo = $1 AND pr_number = $2",
+            &[&repo, &pr_number],
+        )
+        .await
+        .context("deleting assigned PRs")?;
+    for assignee in assignees {
+        transaction
+            .execute(
+                "INSERT INTO assigned_prs (repo, pr_number, username) VALUES ($1, $2, $3) \
+                 ON CONFLICT DO NOTHING",
+                &[&repo, &pr_number, &assignee.to_lowercase()],
+            )
+            .await
+            .context("inserting assigned PR")?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Returns the number of open PRs assigned to each user in the repository,
+/// keyed by the lowercase username.
+pub async fn workloads(db: &DbClient, repo: &str) -> Result<HashMap<String, u32>> {
+    let rows = db
+        .query(
+            "SELECT username, COUNT(*) FROM assigned_prs WHERE repo = $1 GROUP BY username",
+            &[&repo],
+        )
+        .await
+        .context("selecting workloads")?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.get(0), row.get::<_, i64>(1) as u32))
+        .collect())
+}
+
+/// Whether the open PRs of the repository were already backfilled.
+pub async fn is_backfilled(db: &DbClient, repo: &str) -> Result<bool> {
+    let row = db
+        .query_opt(
+            "SELECT 1 FROM assigned_prs_backfills WHERE repo = $1",
+            &[&repo],
+        )
+        .await
+        .context("selecting assigned PRs backfill")?;
+    Ok(row.is_some())
+}
+
+/// Replaces the recorded assignees of every PR in the repository with those
+/// of its open `prs`, and records that the repository was backfilled.
+pub async fn backfill(db: &mut DbClient, repo: &str, prs: &[(u64, Vec<&str>)]) -> Result<()> {
+    let transaction = db.transaction().await?;
+    transaction
+        .execute("DELETE FROM assigned_prs WHERE repo = $1", &[&repo])
+        .await
+        .context("deleting assigned PRs")?;
+    for (pr_number, assignees) in prs {
+        let pr_number = *pr_number as i32;
+        for assignee in assignees {
+            transaction
+                .execute(
+                    "INSERT INTO assigned_prs (repo, pr_number, username) VALUES ($1, $2, $3) \
+                     ON CONFLICT DO NOTHING",
+                    &[&repo, &pr_number, &assignee.to_lowercase()],
+                )
+                .await
+                .context("inserting assigned PR")?;
+        }
+    }
+    transaction
+        .execute(
+            "INSERT INTO assigned_prs_backfills (repo, backfilled_at) VALUES ($1, now()) \
+             ON CONFLICT (repo) DO UPDATE SET backfilled_at = now()",
+            &[&repo],
+        )
+        .await
+        .context("recording assigned PRs backfill")?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -77,3 +77,53 @@ where
         Ok(())
     }
 }
+
+/// Reads the data without locking it, e.g. to decide what to do before making
+/// slow requests. Use [`IssueData::load`] to change it afterwards.
+pub async fn peek<T>(db: &DbClient, issue: &Issue, key: &str) -> Result<T>
+where
+    T: for<'a> Deserialize<'a> + Default,
+{
+    let repo = issue.repository().to_string();
+    let issue_number = issue.number as i32;
+    let data = db
+        .query_opt(
+            "SELECT data FROM issue_data WHERE \
+             repo = $1 AND issue_number = $2 AND key = $3",
+            &[&repo, &issue_number, &key],
+        )
+        .await
+        .context("selecting issue data")?
+        .map(|row| row.get::<usize, Json<T>>(0).0)
+        .unwrap_or_default();
+    Ok(data)
+}
+
+/// Serializes the handlers which update the same data of an issue, until it
+/// is dropped.
+///
+/// Unlike [`IssueData::load`], this doesn't lock the `issue_data` table, so
+/// it can be held while making slow requests, e.g. to decide what to post
+/// and post it in one go. The data is then read with [`peek`] and saved with
+/// [`IssueData`] on another connection.
+pub struct IssueLock<'db> {
+    _transaction: Transaction<'db>,
+}
+
+pub async fn lock<'db>(db: &'db mut DbClient, issue: &Issue, key: &str) -> Result<IssueLock<'db>> {
+    let transaction = db.transaction().await?;
+    // The lock is released when the transaction ends.
+    transaction
+        .execute(
+            "SELECT pg_advisory_xact_lock(hashtext($1), $2)",
+            &[
+                &format!("{}:{key}", issue.repository()),
+                &(issue.number as i32),
+            ],
+        )
+        .await
+        .context("locking issue")?;
+    Ok(IssueLock {
+        _transaction: transaction,
+    })
+}
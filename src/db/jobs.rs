@@ -118,6 +118,17 @@ pub async fn get_jobs_to_execute(db: &DbClient) -> Result<Vec<Job>> {
     Ok(data)
 }
 
+/// Returns the number of jobs which are due, but haven't completed
+/// successfully yet.
+pub async fn count_pending_jobs(db: &DbClient) -> Result<i64> {
+    let row = db
+        .query_one("SELECT count(*) FROM jobs WHERE scheduled_at <= now()", &[])
+        .await
+        .context("Counting pending jobs")?;
+
+    Ok(row.get(0))
+}
+
 fn deserialize_job(row: &tokio_postgres::row::Row) -> Result<Job> {
     let id: Uuid = row.try_get(0)?;
     let name: String = row.try_get(1)?;
@@ -0,0 +1,69 @@
+//! The `vacations` table records the users who set themselves on vacation
+//! with `@rustbot vacation until <date>` (or the Zulip equivalent), so that
+//! they aren't assigned PRs in any repository.
+//!
+//! A vacation ends automatically after its last day, which is in UTC.
+
+use anyhow::{Context as _, Result};
+use chrono::{NaiveDate, Utc};
+use std::collections::HashSet;
+use tokio_postgres::Client as DbClient;
+
+/// Parses the last day of a vacation, in the `yyyy-mm-dd` format, checking
+/// that it isn't in the past.
+pub fn parse_until(until: &str) -> Result<NaiveDate, String> {
+    let date = NaiveDate::parse_from_str(until, "%Y-%m-%d")
+        .map_err(|_| format!("`{until}` is not a valid date, use the format `2026-11-30`."))?;
+    if date < Utc::now().naive_utc().date() {
+        return Err(format!("`{until}` is in the past."));
+    }
+    Ok(date)
+}
+
+/// Sets the user on vacation until the given date, included.
+pub async fn start_vacation(db: &DbClient, username: &str, until: NaiveDate) -> Result<()> {
+    db.execute(
+        "INSERT INTO vacations (username, until) VALUES ($1, $2) \
+         ON CONFLICT (username) DO UPDATE SET until = EXCLUDED.until",
+        &[&username.to_lowercase(), &until],
+    )
+    .await
+    .context("inserting vacation")?;
+    Ok(())
+}
+
+/// Ends the vacation of the user, returning whether they were on vacation.
+pub async fn end_vacation(db: &DbClient, username: &str) -> Result<bool> {
+    let deleted = db
+        .execute(
+            "DELETE FROM vacations WHERE username = $1 AND until >= $2",
+            &[&username.to_lowercase(), &Utc::now().naive_utc().date()],
+        )
+        .await
+        .context("deleting vacation")?;
+    Ok(deleted > 0)
+}
+
+/// Returns the last day of the vacation of the user, if they are on vacation.
+pub async fn vacation_until(db: &DbClient, username: &str) -> Result<Option<NaiveDate>> {
+    let row = db
+        .query_opt(
+            "SELECT until FROM vacations WHERE username = $1 AND until >= $2",
+            &[&username.to_lowercase(), &Utc::now().naive_utc().date()],
+        )
+        .await
+        .context("selecting vacation")?;
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// Returns the lowercase usernames of the users who are on vacation.
+pub async fn users_on_vacation(db: &DbClient) -> Result<HashSet<String>> {
+    let rows = db
+        .query(
+            "SELECT username FROM vacations WHERE until >= $1",
+            &[&Utc::now().naive_utc().date()],
+        )
+        .await
+        .context("selecting vacations")?;
+    Ok(rows.into_iter().map(|row| row.get(0)).collect())
+}
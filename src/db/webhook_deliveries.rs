@@ -0,0 +1,220 @@
+//! The `webhook_deliveries` table is a durable inbox for incoming webhooks.
+//!
+//! Webhooks are stored here as soon as they are received, and GitHub gets an
+//! answer right away. Background workers (see [`crate::db::process_webhook_deliveries`])
+//! then pop them off the queue and run the handlers.
+//!
+//! The `X-GitHub-Delivery` GUID is the primary key, so a redelivery of a
+//! webhook which is already queued or was handled successfully is ignored.
+//!
+//! A delivery is in one of the following states:
+//!
+//! * `pending`: waiting to be processed once `next_attempt_at` has passed.
+//! * `processing`: claimed by a worker, which refreshes `updated_at` every
+//!   [`HEARTBEAT_INTERVAL`] while the handlers run.
+//! * `succeeded`: handled successfully. The payload is dropped at this point.
+//! * `dead`: failed [`MAX_ATTEMPTS`] times and won't be retried automatically.
+//!   These are listed at the `/webhook-deliveries` admin route, and
+//!   redelivering the webhook from GitHub puts it back into the queue.
+//!
+//! A delivery records the handlers which finished in `finished_handlers`, so
+//! that a retry only runs the handlers which failed. The notification handler
+//! is recorded before it runs instead, so that it never pings anyone twice. A
+//! delivery whose payload can't be deserialized is dead right away.
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tokio_postgres::Client as DbClient;
+
+/// How many times a delivery is attempted before it is marked as dead.
+pub const MAX_ATTEMPTS: i32 = 5;
+
+/// The delay before the first retry. It doubles with every further attempt.
+const RETRY_BASE_DELAY_SECS: i64 = 30;
+
+/// How often a worker refreshes the delivery it is processing. A delivery
+/// which wasn't refreshed for [`STALE_AFTER_HEARTBEATS`] times as long is
+/// assumed to be abandoned (e.g. because the server was restarted), and is
+/// claimed again.
+pub const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How many heartbeats a worker may miss before its delivery is claimed again.
+const STALE_AFTER_HEARTBEATS: u32 = 5;
+
+pub struct Delivery {
+    pub delivery_id: String,
+    pub event: String,
+    pub payload: String,
+    /// The number of attempts so far, including the current one.
+    pub attempts: i32,
+    /// The handlers which finished on previous attempts.
+    pub finished_handlers: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeadDelivery {
+    pub delivery_id: String,
+    pub event: String,
+    pub attempts: i32,
+    pub received_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub error_message: Option<String>,
+}
+
+/// Adds a delivery to the queue.
+///
+/// Returns `false` if the delivery is already queued, being processed, or was
+/// handled successfully. Dead deliveries are queued again with a fresh set of
+/// attempts, where the handlers which already finished are still skipped.
+pub async fn enqueue_delivery(
+    db: &DbClient,
+    delivery_id: &str,
+    event: &str,
+    payload: &str,
+) -> Result<bool> {
+    tracing::trace!("enqueue_delivery(delivery_id={})", delivery_id);
+
+    let rows = db
+        .query(
+            "INSERT INTO webhook_deliveries (delivery_id, event, status, attempts, payload)
+                VALUES ($1, $2, 'pending', 0, $3)
+            ON CONFLICT (delivery_id) DO UPDATE
+                SET status = 'pending',
+                    attempts = 0,
+                    payload = EXCLUDED.payload,
+                    next_attempt_at = now(),
+                    updated_at = now()
+                WHERE webhook_deliveries.status = 'dead'
+            RETURNING delivery_id",
+            &[&delivery_id, &event, &payload],
+        )
+        .await
+        .context("Enqueueing webhook delivery")?;
+
+    Ok(!rows.is_empty())
+}
+
+/// Claims the oldest delivery which is ready to be processed.
+///
+/// Deliveries whose worker stopped refreshing them (see
+/// [`HEARTBEAT_INTERVAL`]) are picked up again.
+pub async fn claim_next_delivery(db: &DbClient) -> Result<Option<Delivery>> {
+    let stale_after_secs = (HEARTBEAT_INTERVAL * STALE_AFTER_HEARTBEATS).as_secs_f64();
+    let row = db
+        .query_opt(
+            "UPDATE webhook_deliveries
+                SET status = 'processing', attempts = attempts + 1, updated_at = now()
+            WHERE delivery_id = (
+                SELECT delivery_id FROM webhook_deliveries
+                WHERE payload IS NOT NULL
+                    AND ((status = 'pending' AND next_attempt_at <= now())
+                        OR (status = 'processing' AND updated_at < now() - make_interval(secs => $1)))
+                ORDER BY received_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING delivery_id, event, payload, attempts, finished_handlers",
+            &[&stale_after_secs],
+        )
+        .await
+        .context("Claiming webhook delivery")?;
+
+    Ok(row.map(|row| Delivery {
+        delivery_id: row.get(0),
+        event: row.get(1),
+        payload: row.get(2),
+        attempts: row.get(3),
+        finished_handlers: row.get(4),
+    }))
+}
+
+/// Marks a delivery which is being processed as still alive.
+pub async fn touch_delivery(db: &DbClient, delivery_id: &str) -> Result<()> {
+    db.execute(
+        "UPDATE webhook_deliveries SET updated_at = now()
+        WHERE delivery_id = $1 AND status = 'processing'",
+        &[&delivery_id],
+    )
+    .await
+    .context("Refreshing webhook delivery")?;
+
+    Ok(())
+}
+
+pub async fn complete_delivery(db: &DbClient, delivery_id: &str) -> Result<()> {
+    tracing::trace!("complete_delivery(delivery_id={})", delivery_id);
+
+    db.execute(
+        "UPDATE webhook_deliveries
+            SET status = 'succeeded', payload = NULL, error_message = NULL, updated_at = now()
+        WHERE delivery_id = $1",
+        &[&delivery_id],
+    )
+    .await
+    .context("Completing webhook delivery")?;
+
+    Ok(())
+}
+
+/// Records a failed attempt, scheduling a retry with exponential backoff if it
+/// is `retryable`.
+///
+/// Returns `true` if the delivery ran out of attempts and is now dead.
+pub async fn fail_delivery(
+    db: &DbClient,
+    delivery: &Delivery,
+    message: &str,
+    retryable: bool,
+) -> Result<bool> {
+    tracing::trace!("fail_delivery(delivery_id={})", delivery.delivery_id);
+
+    let dead = !retryable || delivery.attempts >= MAX_ATTEMPTS;
+    let status = if dead { "dead" } else { "pending" };
+    let next_attempt_at = Utc::now() + retry_delay(delivery.attempts);
+    db.execute(
+        "UPDATE webhook_deliveries
+            SET status = $2, error_message = $3, next_attempt_at = $4,
+                finished_handlers = $5, updated_at = now()
+        WHERE delivery_id = $1",
+        &[
+            &delivery.delivery_id,
+            &status,
+            &message,
+            &next_attempt_at,
+            &delivery.finished_handlers,
+        ],
+    )
+    .await
+    .context("Failing webhook delivery")?;
+
+    Ok(dead)
+}
+
+pub async fn get_dead_deliveries(db: &DbClient) -> Result<Vec<DeadDelivery>> {
+    let rows = db
+        .query(
+            "SELECT delivery_id, event, attempts, received_at, updated_at, error_message
+            FROM webhook_deliveries
+            WHERE status = 'dead'
+            ORDER BY updated_at DESC",
+            &[],
+        )
+        .await
+        .context("Getting dead webhook deliveries")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| DeadDelivery {
+            delivery_id: row.get(0),
+            event: row.get(1),
+            attempts: row.get(2),
+            received_at: row.get(3),
+            updated_at: row.get(4),
+            error_message: row.get(5),
+        })
+        .collect())
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::seconds(RETRY_BASE_DELAY_SECS << (attempts - 1).clamp(0, 16))
+}
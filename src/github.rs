@@ -11,10 +11,17 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::{
     fmt,
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 use tracing as log;
 
+pub mod app;
+pub mod discussion;
+
+pub use app::{GithubApp, InstallationEvent};
+pub use discussion::{DiscussionCommentEvent, DiscussionDetails, DiscussionEvent};
+
 #[derive(Debug, PartialEq, Eq, serde::Deserialize)]
 pub struct User {
     pub login: String,
@@ -26,14 +33,37 @@ impl GithubClient {
         const MAX_ATTEMPTS: usize = 2;
         log::debug!("send_req with {:?}", req);
         let req_dbg = format!("{:?}", req);
-        let req = req
+        let mut req = req
             .build()
             .with_context(|| format!("building reqwest {}", req_dbg))?;
 
-        let mut resp = self.client.execute(req.try_clone().unwrap()).await?;
-        if let Some(sleep) = Self::needs_retry(&resp).await {
-            resp = self.retry(req, sleep, MAX_ATTEMPTS).await?;
-        }
+        let resp = if let Some(resp) = self.stand_in.as_ref().and_then(|s| s.respond(&req)) {
+            resp
+        } else {
+            self.authorize(&mut req).await;
+
+            let method = req.method().clone();
+            let resp = async {
+                let mut resp = self.client.execute(req.try_clone().unwrap()).await?;
+                if let Some(sleep) = Self::needs_retry(&resp).await {
+                    resp = self.retry(req, sleep, MAX_ATTEMPTS).await?;
+                }
+                Ok::<_, reqwest::Error>(resp)
+            }
+            .await;
+            let status = match &resp {
+                Ok(resp) => resp.status().as_str().to_string(),
+                Err(_) => "error".to_string(),
+            };
+            crate::metrics::GITHUB_REQUESTS
+                .with_label_values(&[method.as_str(), &status])
+                .inc();
+            let resp = resp?;
+            if resp.status() != StatusCode::UNAUTHORIZED {
+                crate::health::GITHUB_TOKEN.beat();
+            }
+            resp
+        };
         let maybe_err = resp.error_for_status_ref().err();
         let body = resp
             .bytes()
@@ -241,6 +271,9 @@ pub struct PullRequestDetails {
 /// fields, this struct is used for both. The `pull_request` field can be used
 /// to determine which it is. Some fields are only available on pull requests
 /// (but not always, check the GitHub API for details).
+///
+/// Discussions are also represented by this struct, see the `discussion`
+/// field.
 #[derive(Debug, serde::Deserialize)]
 pub struct Issue {
     pub number: u64,
@@ -269,6 +302,12 @@ pub struct Issue {
     /// this does not always get filled in by GitHub, and must be manually
     /// populated (because some webhook events do not set it).
     pub pull_request: Option<PullRequestDetails>,
+    /// Indicator if this is a discussion.
+    ///
+    /// This is `Some` if the issue was converted from a discussion with
+    /// [`discussion::Discussion::into_issue`]. It is never deserialized.
+    #[serde(skip)]
+    pub discussion: Option<DiscussionDetails>,
     /// Whether or not the pull request was merged.
     #[serde(default)]
     pub merged: bool,
@@ -328,6 +367,10 @@ impl ZulipGitHubReference {
 
 #[derive(Debug, serde::Deserialize)]
 pub struct Comment {
+    pub id: usize,
+    /// The GraphQL node ID, which is needed to react to discussion comments.
+    #[serde(default)]
+    pub node_id: String,
     #[serde(deserialize_with = "opt_string")]
     pub body: String,
     pub html_url: String,
@@ -336,6 +379,22 @@ pub struct Comment {
     pub updated_at: chrono::DateTime<Utc>,
     #[serde(default, rename = "state")]
     pub pr_review_state: Option<PullRequestReviewState>,
+    /// What the comment was made on, which isn't part of the payload but
+    /// set when the webhook is received.
+    #[serde(skip)]
+    pub kind: CommentKind,
+}
+
+/// What a [`Comment`] was made on, which decides how to react to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// A comment on an issue, PR, or discussion.
+    #[default]
+    Issue,
+    /// A comment on the diff of a PR.
+    ReviewComment,
+    /// The body of a PR review.
+    Review,
 }
 
 #[derive(Debug, serde::Deserialize, Eq, PartialEq)]
@@ -348,6 +407,50 @@ pub enum PullRequestReviewState {
     Pending,
 }
 
+/// A reaction to an issue or a comment.
+///
+/// <https://docs.github.com/en/rest/reactions/reactions#about-reactions>
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Reaction {
+    /// 👍
+    #[serde(rename = "+1")]
+    ThumbsUp,
+    /// 👎
+    #[serde(rename = "-1")]
+    ThumbsDown,
+    /// 😄
+    Laugh,
+    /// 😕
+    Confused,
+    /// ❤️
+    Heart,
+    /// 🎉
+    Hooray,
+    /// 🚀
+    Rocket,
+    /// 👀
+    Eyes,
+}
+
+impl Reaction {
+    /// The name of the reaction in the GraphQL API.
+    fn graphql_name(self) -> &'static str {
+        match self {
+            Reaction::ThumbsUp => "THUMBS_UP",
+            Reaction::ThumbsDown => "THUMBS_DOWN",
+            Reaction::Laugh => "LAUGH",
+            Reaction::Confused => "CONFUSED",
+            Reaction::Heart => "HEART",
+            Reaction::Hooray => "HOORAY",
+            Reaction::Rocket => "ROCKET",
+            Reaction::Eyes => "EYES",
+        }
+    }
+}
+
 fn opt_string<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: serde::de::Deserializer<'de>,
@@ -403,7 +506,11 @@ impl IssueRepository {
         )
     }
 
-    async fn has_label(&self, client: &GithubClient, label: &str) -> anyhow::Result<bool> {
+    pub(crate) async fn has_label(
+        &self,
+        client: &GithubClient,
+        label: &str,
+    ) -> anyhow::Result<bool> {
         #[allow(clippy::redundant_pattern_matching)]
         let url = format!("{}/labels/{}", self.url(), label);
         match client.send_req(client.get(&url)).await {
@@ -419,6 +526,29 @@ impl IssueRepository {
             }
         }
     }
+
+    /// Returns every open pull request in the repository.
+    pub(crate) async fn open_pulls(&self, client: &GithubClient) -> anyhow::Result<Vec<Issue>> {
+        let mut pulls = Vec::new();
+        let mut page = 1;
+        loop {
+            let url = format!("{}/pulls?state=open&page={page}&per_page=100", self.url());
+            let new: Vec<Issue> = client
+                .json(client.get(&url))
+                .await
+                .with_context(|| format!("{} failed to list open pulls", self))?;
+            if new.is_empty() {
+                break;
+            }
+            pulls.extend(new);
+
+            page += 1;
+        }
+        for pull in &mut pulls {
+            pull.pull_request = Some(PullRequestDetails {});
+        }
+        Ok(pulls)
+    }
 }
 
 #[derive(Debug)]
@@ -475,12 +605,18 @@ impl Issue {
     }
 
     pub async fn get_comment(&self, client: &GithubClient, id: usize) -> anyhow::Result<Comment> {
+        if self.discussion.is_some() {
+            anyhow::bail!("fetching discussion comments is not supported");
+        }
         let comment_url = format!("{}/issues/comments/{}", self.repository().url(), id);
         let comment = client.json(client.get(&comment_url)).await?;
         Ok(comment)
     }
 
     pub async fn edit_body(&self, client: &GithubClient, body: &str) -> anyhow::Result<()> {
+        if let Some(discussion) = &self.discussion {
+            return discussion::edit_body(client, discussion, body).await;
+        }
         let edit_url = format!("{}/issues/{}", self.repository().url(), self.number);
         #[derive(serde::Serialize)]
         struct ChangedIssue<'a> {
@@ -499,6 +635,9 @@ impl Issue {
         id: usize,
         new_body: &str,
     ) -> anyhow::Result<()> {
+        if self.discussion.is_some() {
+            anyhow::bail!("editing discussion comments is not supported");
+        }
         let comment_url = format!("{}/issues/comments/{}", self.repository().url(), id);
         #[derive(serde::Serialize)]
         struct NewComment<'a> {
@@ -516,6 +655,10 @@ impl Issue {
     }
 
     pub async fn post_comment(&self, client: &GithubClient, body: &str) -> anyhow::Result<()> {
+        if let Some(discussion) = &self.discussion {
+            discussion::post_comment(client, discussion, body).await?;
+            return Ok(());
+        }
         #[derive(serde::Serialize)]
         struct PostComment<'a> {
             body: &'a str,
@@ -527,6 +670,93 @@ impl Issue {
         Ok(())
     }
 
+    /// Like [`Issue::post_comment`], but returns the new comment, e.g. so
+    /// that it can be edited later.
+    pub async fn create_comment(
+        &self,
+        client: &GithubClient,
+        body: &str,
+    ) -> anyhow::Result<Comment> {
+        if let Some(discussion) = &self.discussion {
+            return discussion::create_comment(client, discussion, body).await;
+        }
+        #[derive(serde::Serialize)]
+        struct PostComment<'a> {
+            body: &'a str,
+        }
+        client
+            .json(client.post(&self.comments_url).json(&PostComment { body }))
+            .await
+            .context("failed to post comment")
+    }
+
+    /// Edits the comment with the given ID, or posts a new one if there is
+    /// none (or it was deleted). Returns the ID of the comment.
+    pub async fn upsert_comment(
+        &self,
+        client: &GithubClient,
+        comment_id: Option<usize>,
+        body: &str,
+    ) -> anyhow::Result<usize> {
+        if let Some(id) = comment_id {
+            match self.edit_comment(client, id, body).await {
+                Ok(()) => return Ok(id),
+                Err(e) => log::warn!(
+                    "failed to edit comment {id} on {}, posting a new one: {e:?}",
+                    self.global_id()
+                ),
+            }
+        }
+        let comment = self.create_comment(client, body).await?;
+        Ok(comment.id)
+    }
+
+    /// Adds a reaction to the body of the issue.
+    pub async fn add_reaction(
+        &self,
+        client: &GithubClient,
+        reaction: Reaction,
+    ) -> anyhow::Result<()> {
+        if let Some(discussion) = &self.discussion {
+            return discussion::add_reaction(client, &discussion.node_id, reaction).await;
+        }
+        // POST /repos/:owner/:repo/issues/:number/reactions
+        let url = format!(
+            "{}/issues/{}/reactions",
+            self.repository().url(),
+            self.number
+        );
+        post_reaction(client, &url, reaction).await
+    }
+
+    /// Adds a reaction to a comment on the issue.
+    pub async fn add_comment_reaction(
+        &self,
+        client: &GithubClient,
+        comment: &Comment,
+        reaction: Reaction,
+    ) -> anyhow::Result<()> {
+        if self.discussion.is_some() {
+            return discussion::add_reaction(client, &comment.node_id, reaction).await;
+        }
+        let url = match comment.kind {
+            // POST /repos/:owner/:repo/issues/comments/:id/reactions
+            CommentKind::Issue => format!(
+                "{}/issues/comments/{}/reactions",
+                self.repository().url(),
+                comment.id
+            ),
+            // POST /repos/:owner/:repo/pulls/comments/:id/reactions
+            CommentKind::ReviewComment => format!(
+                "{}/pulls/comments/{}/reactions",
+                self.repository().url(),
+                comment.id
+            ),
+            CommentKind::Review => anyhow::bail!("GitHub doesn't support reactions to reviews"),
+        };
+        post_reaction(client, &url, reaction).await
+    }
+
     pub async fn remove_label(&self, client: &GithubClient, label: &str) -> anyhow::Result<()> {
         log::info!("remove_label from {}: {:?}", self.global_id(), label);
         // DELETE /repos/:owner/:repo/issues/:number/labels/{name}
@@ -546,6 +776,10 @@ impl Issue {
             return Ok(());
         }
 
+        if let Some(discussion) = &self.discussion {
+            return discussion::remove_label(client, self, discussion, label).await;
+        }
+
         client
             .send_req(client.delete(&url))
             .await
@@ -581,6 +815,10 @@ impl Issue {
             return Ok(());
         }
 
+        if let Some(discussion) = &self.discussion {
+            return discussion::add_labels(client, self, discussion, &labels).await;
+        }
+
         let mut unknown_labels = vec![];
         let mut known_labels = vec![];
         for label in labels {
@@ -757,6 +995,9 @@ impl Issue {
     }
 
     pub async fn close(&self, client: &GithubClient) -> anyhow::Result<()> {
+        if let Some(discussion) = &self.discussion {
+            return discussion::close(client, discussion).await;
+        }
         let edit_url = format!("{}/issues/{}", self.repository().url(), self.number);
         #[derive(serde::Serialize)]
         struct CloseIssue<'a> {
@@ -820,17 +1061,31 @@ impl Issue {
         Ok(commits)
     }
 
+    /// Returns the files changed by this pull request (no files are returned
+    /// if this `Issue` is not a pull request).
     pub async fn files(&self, client: &GithubClient) -> anyhow::Result<Vec<PullRequestFile>> {
         if !self.is_pr() {
             return Ok(vec![]);
         }
 
-        let req = client.get(&format!(
-            "{}/pulls/{}/files",
-            self.repository().url(),
-            self.number
-        ));
-        Ok(client.json(req).await?)
+        let mut files = Vec::new();
+        let mut page = 1;
+        loop {
+            let req = client.get(&format!(
+                "{}/pulls/{}/files?page={page}&per_page=100",
+                self.repository().url(),
+                self.number
+            ));
+
+            let new: Vec<_> = client.json(req).await?;
+            if new.is_empty() {
+                break;
+            }
+            files.extend(new);
+
+            page += 1;
+        }
+        Ok(files)
     }
 }
 
@@ -954,7 +1209,7 @@ struct PullRequestEventFields {}
 
 #[derive(Clone, Debug, serde::Deserialize)]
 pub struct CommitBase {
-    sha: String,
+    pub sha: String,
     #[serde(rename = "ref")]
     pub git_ref: String,
     pub repo: Repository,
@@ -1190,6 +1445,65 @@ impl Repository {
             .with_context(|| format!("{} failed to get git commit {sha}", self.full_name))
     }
 
+    /// Retrieves a pull request by number.
+    pub async fn get_pr(&self, client: &GithubClient, number: u64) -> anyhow::Result<Issue> {
+        let url = format!("{}/pulls/{number}", self.url());
+        let mut issue: Issue = client
+            .json(client.get(&url))
+            .await
+            .with_context(|| format!("{} failed to get pr {number}", self.full_name))?;
+        issue.pull_request = Some(PullRequestDetails {});
+        Ok(issue)
+    }
+
+    /// Returns the open or merged pull requests which contain the given
+    /// commit.
+    pub async fn pulls_for_commit(
+        &self,
+        client: &GithubClient,
+        sha: &str,
+    ) -> anyhow::Result<Vec<Issue>> {
+        let url = format!("{}/commits/{sha}/pulls", self.url());
+        let mut issues: Vec<Issue> = client
+            .json(client.get(&url))
+            .await
+            .with_context(|| format!("{} failed to get pulls for commit {sha}", self.full_name))?;
+        for issue in &mut issues {
+            issue.pull_request = Some(PullRequestDetails {});
+        }
+        Ok(issues)
+    }
+
+    /// Returns the check runs for the given commit.
+    pub async fn check_runs(
+        &self,
+        client: &GithubClient,
+        sha: &str,
+    ) -> anyhow::Result<Vec<CheckRun>> {
+        #[derive(serde::Deserialize)]
+        struct CheckRuns {
+            check_runs: Vec<CheckRun>,
+        }
+        let mut runs = Vec::new();
+        let mut page = 1;
+        loop {
+            let url = format!(
+                "{}/commits/{sha}/check-runs?page={page}&per_page=100",
+                self.url()
+            );
+            let new: CheckRuns = client.json(client.get(&url)).await.with_context(|| {
+                format!("{} failed to get check runs for {sha}", self.full_name)
+            })?;
+            if new.check_runs.is_empty() {
+                break;
+            }
+            runs.extend(new.check_runs);
+
+            page += 1;
+        }
+        Ok(runs)
+    }
+
     /// Creates a new commit.
     pub async fn create_commit(
         &self,
@@ -1670,6 +1984,150 @@ pub struct CreateEvent {
 pub struct PushEvent {
     #[serde(rename = "ref")]
     pub git_ref: String,
+    /// The SHA of the commit the ref points to after the push.
+    pub after: String,
+    /// The pushed commits. GitHub includes at most 2048 of them.
+    #[serde(default)]
+    pub commits: Vec<PushCommit>,
+    repository: Repository,
+    sender: User,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PushCommit {
+    pub id: String,
+    #[serde(default)]
+    pub added: Vec<String>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+    #[serde(default)]
+    pub modified: Vec<String>,
+}
+
+impl PushEvent {
+    /// Whether this push updates the default branch of the repository.
+    pub fn is_default_branch(&self) -> bool {
+        self.git_ref == format!("refs/heads/{}", self.repository.default_branch)
+    }
+
+    /// Whether any of the pushed commits add, remove or modify `path`.
+    pub fn touches(&self, path: &str) -> bool {
+        self.commits.iter().any(|commit| {
+            commit
+                .added
+                .iter()
+                .chain(&commit.removed)
+                .chain(&commit.modified)
+                .any(|p| p == path)
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Queued,
+    InProgress,
+    Completed,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckConclusion {
+    Success,
+    Failure,
+    Neutral,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+    StartupFailure,
+    Stale,
+    #[serde(other)]
+    Other,
+}
+
+impl CheckConclusion {
+    /// Whether this conclusion means that something needs to be fixed.
+    pub fn is_failure(self) -> bool {
+        matches!(
+            self,
+            CheckConclusion::Failure | CheckConclusion::TimedOut | CheckConclusion::StartupFailure
+        )
+    }
+}
+
+/// A pull request, as referenced by checks and workflow runs.
+///
+/// Note that GitHub leaves these lists empty for pull requests from forks.
+#[derive(Debug, serde::Deserialize)]
+pub struct CheckPullRequest {
+    pub number: u64,
+    pub head: CheckPullRequestHead,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CheckPullRequestHead {
+    pub sha: String,
+}
+
+/// <https://docs.github.com/en/rest/checks/runs>
+#[derive(Debug, serde::Deserialize)]
+pub struct CheckRun {
+    pub name: String,
+    pub head_sha: String,
+    pub status: CheckStatus,
+    pub conclusion: Option<CheckConclusion>,
+    /// The URL of the check on GitHub.
+    pub html_url: Option<String>,
+    /// The URL of the integration's site with the details (e.g. logs) of the
+    /// check.
+    pub details_url: Option<String>,
+    #[serde(default)]
+    pub pull_requests: Vec<CheckPullRequest>,
+}
+
+/// <https://docs.github.com/en/rest/checks/suites>
+#[derive(Debug, serde::Deserialize)]
+pub struct CheckSuite {
+    pub head_sha: String,
+    pub status: Option<CheckStatus>,
+    pub conclusion: Option<CheckConclusion>,
+    #[serde(default)]
+    pub pull_requests: Vec<CheckPullRequest>,
+}
+
+/// <https://docs.github.com/en/rest/actions/workflow-runs>
+#[derive(Debug, serde::Deserialize)]
+pub struct WorkflowRun {
+    pub name: Option<String>,
+    pub head_sha: String,
+    pub status: Option<CheckStatus>,
+    pub conclusion: Option<CheckConclusion>,
+    pub html_url: String,
+    #[serde(default)]
+    pub pull_requests: Vec<CheckPullRequest>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CheckRunEvent {
+    pub check_run: CheckRun,
+    repository: Repository,
+    sender: User,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CheckSuiteEvent {
+    pub check_suite: CheckSuite,
+    repository: Repository,
+    sender: User,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct WorkflowRunEvent {
+    pub workflow_run: WorkflowRun,
     repository: Repository,
     sender: User,
 }
@@ -1677,6 +2135,10 @@ pub struct PushEvent {
 /// An event triggered by a webhook.
 #[derive(Debug)]
 pub enum Event {
+    /// A check run was created, completed, or re-requested.
+    CheckRun(CheckRunEvent),
+    /// A check suite was completed or (re-)requested.
+    CheckSuite(CheckSuiteEvent),
     /// A Git branch or tag is created.
     Create(CreateEvent),
     /// A comment on an issue or PR.
@@ -1693,75 +2155,131 @@ pub enum Event {
     Issue(IssuesEvent),
     /// One or more commits are pushed to a repository branch or tag.
     Push(PushEvent),
+    /// A GitHub Actions workflow run was requested, started, or completed.
+    WorkflowRun(WorkflowRunEvent),
 }
 
 impl Event {
     pub fn repo(&self) -> &Repository {
         match self {
+            Event::CheckRun(event) => &event.repository,
+            Event::CheckSuite(event) => &event.repository,
             Event::Create(event) => &event.repository,
             Event::IssueComment(event) => &event.repository,
             Event::Issue(event) => &event.repository,
             Event::Push(event) => &event.repository,
+            Event::WorkflowRun(event) => &event.repository,
         }
     }
 
     pub fn issue(&self) -> Option<&Issue> {
         match self {
+            Event::CheckRun(_) => None,
+            Event::CheckSuite(_) => None,
             Event::Create(_) => None,
             Event::IssueComment(event) => Some(&event.issue),
             Event::Issue(event) => Some(&event.issue),
             Event::Push(_) => None,
+            Event::WorkflowRun(_) => None,
         }
     }
 
     /// This will both extract from IssueComment events but also Issue events
     pub fn comment_body(&self) -> Option<&str> {
         match self {
+            Event::CheckRun(_) => None,
+            Event::CheckSuite(_) => None,
             Event::Create(_) => None,
             Event::Issue(e) => Some(&e.issue.body),
             Event::IssueComment(e) => Some(&e.comment.body),
             Event::Push(_) => None,
+            Event::WorkflowRun(_) => None,
         }
     }
 
     /// This will both extract from IssueComment events but also Issue events
     pub fn comment_from(&self) -> Option<&str> {
         match self {
+            Event::CheckRun(_) => None,
+            Event::CheckSuite(_) => None,
             Event::Create(_) => None,
             Event::Issue(e) => Some(&e.changes.as_ref()?.body.as_ref()?.from),
             Event::IssueComment(e) => Some(&e.changes.as_ref()?.body.as_ref()?.from),
             Event::Push(_) => None,
+            Event::WorkflowRun(_) => None,
         }
     }
 
     pub fn html_url(&self) -> Option<&str> {
         match self {
+            Event::CheckRun(_) => None,
+            Event::CheckSuite(_) => None,
             Event::Create(_) => None,
             Event::Issue(e) => Some(&e.issue.html_url),
             Event::IssueComment(e) => Some(&e.comment.html_url),
             Event::Push(_) => None,
+            Event::WorkflowRun(_) => None,
         }
     }
 
     pub fn user(&self) -> &User {
         match self {
+            Event::CheckRun(e) => &e.sender,
+            Event::CheckSuite(e) => &e.sender,
             Event::Create(e) => &e.sender,
             Event::Issue(e) => &e.issue.user,
             Event::IssueComment(e) => &e.comment.user,
             Event::Push(e) => &e.sender,
+            Event::WorkflowRun(e) => &e.sender,
         }
     }
 
     pub fn time(&self) -> Option<chrono::DateTime<FixedOffset>> {
         match self {
+            Event::CheckRun(_) => None,
+            Event::CheckSuite(_) => None,
             Event::Create(_) => None,
             Event::Issue(e) => Some(e.issue.created_at.into()),
             Event::IssueComment(e) => Some(e.comment.updated_at.into()),
             Event::Push(_) => None,
+            Event::WorkflowRun(_) => None,
+        }
+    }
+
+    /// Reacts to the comment, or to the body of the issue for issue events.
+    pub async fn add_reaction(
+        &self,
+        client: &GithubClient,
+        reaction: Reaction,
+    ) -> anyhow::Result<()> {
+        match self {
+            Event::Issue(e) => e.issue.add_reaction(client, reaction).await,
+            Event::IssueComment(e) => {
+                e.issue
+                    .add_comment_reaction(client, &e.comment, reaction)
+                    .await
+            }
+            Event::CheckRun(_)
+            | Event::CheckSuite(_)
+            | Event::Create(_)
+            | Event::Push(_)
+            | Event::WorkflowRun(_) => anyhow::bail!("the event has nothing to react to"),
         }
     }
 }
 
+async fn post_reaction(client: &GithubClient, url: &str, reaction: Reaction) -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    struct NewReaction {
+        content: Reaction,
+    }
+    client
+        .send_req(client.post(url).json(&NewReaction { content: reaction }))
+        .await
+        .context("failed to add reaction")?;
+    Ok(())
+}
+
 trait RequestSend: Sized {
     fn configure(self, g: &GithubClient) -> Self;
 }
@@ -1804,25 +2322,114 @@ fn get_token_from_git_config() -> anyhow::Result<String> {
     Ok(git_token)
 }
 
+/// A local stand-in for the GitHub API.
+///
+/// This is used by the `replay` binary to intercept requests instead of
+/// sending them to GitHub. Besides GitHub API requests, it also sees the
+/// requests sent with [`GithubClient::send_raw`], such as those to Zulip.
+pub trait GithubStandIn: Send + Sync {
+    /// Returns the response to use for the given request, or `None` if the
+    /// request should be sent as usual.
+    fn respond(&self, req: &Request) -> Option<Response>;
+}
+
 #[derive(Clone)]
 pub struct GithubClient {
     token: String,
     client: Client,
+    stand_in: Option<Arc<dyn GithubStandIn>>,
+    app: Option<Arc<GithubApp>>,
 }
 
 impl GithubClient {
     pub fn new(client: Client, token: String) -> Self {
-        GithubClient { client, token }
+        GithubClient {
+            client,
+            token,
+            stand_in: None,
+            app: None,
+        }
+    }
+
+    /// Authenticates requests for accounts which installed the given GitHub
+    /// App with an installation token. Requests which aren't for a specific
+    /// account (such as GraphQL queries without an owner) still use the
+    /// personal access token.
+    pub fn with_app(mut self, app: Arc<GithubApp>) -> Self {
+        self.app = Some(app);
+        self
     }
 
+    pub fn app(&self) -> Option<&GithubApp> {
+        self.app.as_deref()
+    }
+
+    /// Returns an installation token for repositories owned by `owner`, or
+    /// `None` if the personal access token should be used instead.
+    ///
+    /// Failing to get an installation token isn't fatal: the error is logged
+    /// and the personal access token is used.
+    pub async fn installation_token(&self, owner: &str) -> Option<String> {
+        let app = self.app.as_ref()?;
+        match app.token_for_owner(&self.client, owner).await {
+            Ok(token) => token,
+            Err(e) => {
+                log::warn!(
+                    "failed to get an installation token for {owner}, \
+                     using the personal access token: {e:?}"
+                );
+                None
+            }
+        }
+    }
+
+    /// Replaces the personal access token on the request with an installation
+    /// token, if the app is installed for the owner of the repository the
+    /// request is for.
+    async fn authorize(&self, req: &mut Request) {
+        if self.app.is_none() {
+            return;
+        }
+        let Some(owner) = app::request_owner(req) else {
+            return;
+        };
+        if let Some(token) = self.installation_token(&owner).await {
+            let mut auth = HeaderValue::from_maybe_shared(format!("token {}", token)).unwrap();
+            auth.set_sensitive(true);
+            req.headers_mut().insert(AUTHORIZATION, auth);
+        }
+    }
+
+    /// Routes API requests through the given stand-in before they are sent.
+    pub fn with_stand_in(mut self, stand_in: Arc<dyn GithubStandIn>) -> Self {
+        self.stand_in = Some(stand_in);
+        self
+    }
+
+    /// Creates a client with the token from the environment, which also
+    /// uses the GitHub App from the environment if one is configured.
     pub fn new_with_default_token(client: Client) -> Self {
-        Self::new(client, default_token_from_env())
+        let github = Self::new(client, default_token_from_env());
+        match GithubApp::from_env() {
+            Some(app) => github.with_app(Arc::new(app)),
+            None => github,
+        }
     }
 
     pub fn raw(&self) -> &Client {
         &self.client
     }
 
+    /// Sends a request built with [`GithubClient::raw`], e.g. to Zulip, unless
+    /// the stand-in answers it.
+    pub async fn send_raw(&self, req: RequestBuilder) -> reqwest::Result<Response> {
+        let req = req.build()?;
+        match self.stand_in.as_ref().and_then(|s| s.respond(&req)) {
+            Some(resp) => Ok(resp),
+            None => self.client.execute(req).await,
+        }
+    }
+
     pub async fn raw_file(
         &self,
         repo: &str,
@@ -1835,10 +2442,16 @@ impl GithubClient {
         );
         let req = self.get(&url);
         let req_dbg = format!("{:?}", req);
-        let req = req
+        let mut req = req
             .build()
             .with_context(|| format!("failed to build request {:?}", req_dbg))?;
-        let resp = self.client.execute(req).await.context(req_dbg.clone())?;
+        let resp = match self.stand_in.as_ref().and_then(|s| s.respond(&req)) {
+            Some(resp) => resp,
+            None => {
+                self.authorize(&mut req).await;
+                self.client.execute(req).await.context(req_dbg.clone())?
+            }
+        };
         let status = resp.status();
         let body = resp
             .bytes()
@@ -1862,7 +2475,7 @@ impl GithubClient {
         filename: &str,
     ) -> anyhow::Result<String> {
         let url = html_url.replace("github.com", "githubusercontent.com") + "/raw/" + filename;
-        let response = self.raw().get(&url).send().await?;
+        let response = self.send_raw(self.raw().get(&url)).await?;
         response.text().await.context("raw gist from url")
     }
 
@@ -2483,4 +3096,41 @@ index c58310947d2..3b0854d4a9b 100644
             ]
         )
     }
+
+    #[test]
+    fn push_touches_config() {
+        let payload = serde_json::json!({
+            "ref": "refs/heads/master",
+            "after": "b2",
+            "commits": [
+                { "id": "a1", "added": [], "removed": [], "modified": ["README.md"] },
+                { "id": "b2", "added": [], "removed": [], "modified": ["triagebot.toml"] },
+            ],
+            "repository": { "full_name": "rust-lang/rust", "default_branch": "master" },
+            "sender": { "login": "octocat", "id": 1 },
+        });
+        let push: PushEvent = serde_json::from_value(payload).unwrap();
+        assert!(push.is_default_branch());
+        assert!(push.touches("triagebot.toml"));
+        assert!(!push.touches("src/main.rs"));
+
+        let payload = serde_json::json!({
+            "ref": "refs/tags/1.0.0",
+            "after": "c3",
+            "repository": { "full_name": "rust-lang/rust", "default_branch": "master" },
+            "sender": { "login": "octocat", "id": 1 },
+        });
+        let push: PushEvent = serde_json::from_value(payload).unwrap();
+        assert!(!push.is_default_branch());
+        assert!(!push.touches("triagebot.toml"));
+    }
+
+    #[test]
+    fn reaction_names() {
+        assert_eq!(serde_json::json!(Reaction::ThumbsUp), "+1");
+        assert_eq!(serde_json::json!(Reaction::Confused), "confused");
+        assert_eq!(Reaction::ThumbsDown.graphql_name(), "THUMBS_DOWN");
+        let reaction: Reaction = serde_json::from_value(serde_json::json!("-1")).unwrap();
+        assert_eq!(reaction, Reaction::ThumbsDown);
+    }
 }
@@ -0,0 +1,316 @@
+//! Authentication as a GitHub App.
+//!
+//! When `GITHUB_APP_ID` and `GITHUB_APP_PRIVATE_KEY` are set, requests for
+//! repositories of an account which installed the app are sent with an
+//! installation token instead of the personal access token, so that each
+//! installation gets its own rate limit.
+//!
+//! See <https://docs.github.com/en/apps/creating-github-apps/authenticating-with-a-github-app/about-authentication-with-a-github-app>.
+
+use anyhow::Context as _;
+use chrono::{DateTime, Duration, Utc};
+use openssl::{hash::MessageDigest, pkey::PKey, pkey::Private, sign::Signer};
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::{Client, Request};
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use tracing as log;
+
+/// How long before its expiry an installation token is replaced.
+const TOKEN_EXPIRY_MARGIN_MINS: i64 = 5;
+
+pub struct GithubApp {
+    app_id: String,
+    key: PKey<Private>,
+    /// Installation IDs keyed by the lowercase login of the account the app
+    /// is installed on. This is `None` until the installations are loaded.
+    installations: RwLock<Option<HashMap<String, u64>>>,
+    /// Installation tokens keyed by installation ID.
+    tokens: Mutex<HashMap<u64, InstallationToken>>,
+}
+
+#[derive(Clone, serde::Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Installation {
+    pub id: u64,
+    pub account: super::User,
+}
+
+/// <https://docs.github.com/en/webhooks/webhook-events-and-payloads#installation>
+#[derive(Debug, serde::Deserialize)]
+pub struct InstallationEvent {
+    pub action: InstallationAction,
+    pub installation: Installation,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallationAction {
+    Created,
+    Deleted,
+    NewPermissionsAccepted,
+    Suspend,
+    Unsuspend,
+}
+
+impl GithubApp {
+    pub fn new(app_id: String, private_key_pem: &[u8]) -> anyhow::Result<GithubApp> {
+        let key = PKey::private_key_from_pem(private_key_pem)
+            .context("failed to parse the GitHub App private key")?;
+        Ok(GithubApp {
+            app_id,
+            key,
+            installations: RwLock::new(None),
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Creates the app from `GITHUB_APP_ID` and `GITHUB_APP_PRIVATE_KEY`, if
+    /// they are set. Panics if the private key is invalid.
+    pub fn from_env() -> Option<GithubApp> {
+        let app_id = std::env::var("GITHUB_APP_ID").ok()?;
+        let key = std::env::var("GITHUB_APP_PRIVATE_KEY").expect("Missing GITHUB_APP_PRIVATE_KEY");
+        Some(GithubApp::new(app_id, key.as_bytes()).unwrap())
+    }
+
+    /// Creates a JWT to authenticate as the app itself.
+    fn jwt(&self) -> anyhow::Result<String> {
+        let now = Utc::now().timestamp();
+        let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+        let claims = serde_json::json!({
+            // Allow for some clock drift.
+            "iat": now - 60,
+            "exp": now + 9 * 60,
+            "iss": self.app_id,
+        });
+        let mut jwt = format!(
+            "{}.{}",
+            base64_url(header.to_string().as_bytes()),
+            base64_url(claims.to_string().as_bytes())
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(jwt.as_bytes())?;
+        let signature = signer.sign_to_vec()?;
+        jwt.push('.');
+        jwt.push_str(&base64_url(&signature));
+        Ok(jwt)
+    }
+
+    fn app_request(&self, req: reqwest::RequestBuilder) -> anyhow::Result<reqwest::RequestBuilder> {
+        Ok(req
+            .header(USER_AGENT, "rust-lang-triagebot")
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(AUTHORIZATION, format!("Bearer {}", self.jwt()?)))
+    }
+
+    async fn load_installations(&self, client: &Client) -> anyhow::Result<HashMap<String, u64>> {
+        let mut installations = HashMap::new();
+        let mut page = 1;
+        loop {
+            let url = format!("https://api.github.com/app/installations?per_page=100&page={page}");
+            let batch: Vec<Installation> = self
+                .app_request(client.get(&url))?
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .context("failed to list GitHub App installations")?;
+            let done = batch.len() < 100;
+            for installation in batch {
+                installations.insert(installation.account.login.to_lowercase(), installation.id);
+            }
+            if done {
+                break;
+            }
+            page += 1;
+        }
+        log::info!("loaded {} GitHub App installations", installations.len());
+        Ok(installations)
+    }
+
+    async fn installation_id(&self, client: &Client, owner: &str) -> anyhow::Result<Option<u64>> {
+        let owner = owner.to_lowercase();
+        let cached = self
+            .installations
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|installations| installations.get(&owner).copied());
+        if let Some(id) = cached {
+            return Ok(id);
+        }
+        let installations = self.load_installations(client).await?;
+        let id = installations.get(&owner).copied();
+        *self.installations.write().unwrap() = Some(installations);
+        Ok(id)
+    }
+
+    /// Returns an installation token for repositories owned by `owner`, or
+    /// `None` if the app isn't installed there.
+    pub async fn token_for_owner(
+        &self,
+        client: &Client,
+        owner: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(id) = self.installation_id(client, owner).await? else {
+            return Ok(None);
+        };
+        let cached = self.tokens.lock().unwrap().get(&id).cloned();
+        if let Some(token) = cached {
+            if token.expires_at - Duration::minutes(TOKEN_EXPIRY_MARGIN_MINS) > Utc::now() {
+                return Ok(Some(token.token));
+            }
+        }
+
+        log::debug!("minting installation token for {owner} (installation {id})");
+        let url = format!("https://api.github.com/app/installations/{id}/access_tokens");
+        let token: InstallationToken = self
+            .app_request(client.post(&url))?
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("failed to create an installation token for {owner}"))?;
+        self.tokens.lock().unwrap().insert(id, token.clone());
+        Ok(Some(token.token))
+    }
+
+    /// Keeps the list of installations up to date with `installation`
+    /// webhooks.
+    pub fn handle_installation_event(&self, event: &InstallationEvent) {
+        let owner = event.installation.account.login.to_lowercase();
+        let id = event.installation.id;
+        let mut installations = self.installations.write().unwrap();
+        // If the installations haven't been loaded yet, they will include
+        // this change once they are.
+        let Some(installations) = installations.as_mut() else {
+            return;
+        };
+        match event.action {
+            InstallationAction::Created
+            | InstallationAction::NewPermissionsAccepted
+            | InstallationAction::Unsuspend => {
+                log::info!("GitHub App installed for {owner} (installation {id})");
+                installations.insert(owner, id);
+            }
+            InstallationAction::Deleted | InstallationAction::Suspend => {
+                log::info!("GitHub App removed for {owner} (installation {id})");
+                installations.remove(&owner);
+                self.tokens.lock().unwrap().remove(&id);
+            }
+        }
+    }
+}
+
+/// Returns the account which owns the repository a request is for, if any.
+///
+/// For GraphQL queries, this is the `owner` or `repository_owner` variable.
+pub(super) fn request_owner(req: &Request) -> Option<String> {
+    let url = req.url();
+    let mut segments = url.path_segments()?;
+    match url.host_str()? {
+        "api.github.com" => match segments.next()? {
+            "repos" | "orgs" => segments.next().map(|s| s.to_string()),
+            "graphql" => {
+                let body: serde_json::Value =
+                    serde_json::from_slice(req.body()?.as_bytes()?).ok()?;
+                let vars = &body["variables"];
+                vars["owner"]
+                    .as_str()
+                    .or_else(|| vars["repository_owner"].as_str())
+                    .map(|s| s.to_string())
+            }
+            _ => None,
+        },
+        "raw.githubusercontent.com" => segments.next().map(|s| s.to_string()),
+        _ => None,
+    }
+}
+
+fn base64_url(data: &[u8]) -> String {
+    openssl::base64::encode_block(data)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Verifier;
+
+    fn base64_url_decode(data: &str) -> Vec<u8> {
+        let mut data = data.replace('-', "+").replace('_', "/");
+        while data.len() % 4 != 0 {
+            data.push('=');
+        }
+        openssl::base64::decode_block(&data).unwrap()
+    }
+
+    #[test]
+    fn jwt_is_signed_with_the_app_key() {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let app =
+            GithubApp::new("1234".to_string(), &key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        let jwt = app.jwt().unwrap();
+        let parts: Vec<&str> = jwt.split('.').collect();
+        assert_eq!(parts.len(), 3);
+
+        let header: serde_json::Value =
+            serde_json::from_slice(&base64_url_decode(parts[0])).unwrap();
+        assert_eq!(header["alg"], "RS256");
+        let claims: serde_json::Value =
+            serde_json::from_slice(&base64_url_decode(parts[1])).unwrap();
+        assert_eq!(claims["iss"], "1234");
+        assert!(claims["exp"].as_i64().unwrap() > Utc::now().timestamp());
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &key).unwrap();
+        verifier
+            .update(format!("{}.{}", parts[0], parts[1]).as_bytes())
+            .unwrap();
+        assert!(verifier.verify(&base64_url_decode(parts[2])).unwrap());
+    }
+
+    #[test]
+    fn owner_of_request() {
+        let client = Client::new();
+        let owner = |url: &str| request_owner(&client.get(url).build().unwrap());
+        assert_eq!(
+            owner("https://api.github.com/repos/rust-lang/rust/issues/1").as_deref(),
+            Some("rust-lang")
+        );
+        assert_eq!(
+            owner("https://raw.githubusercontent.com/rust-lang/rust/master/triagebot.toml")
+                .as_deref(),
+            Some("rust-lang")
+        );
+        assert_eq!(owner("https://api.github.com/graphql"), None);
+        assert_eq!(owner("https://api.github.com/user"), None);
+
+        let graphql = |vars: serde_json::Value| {
+            let req = client
+                .post("https://api.github.com/graphql")
+                .json(&serde_json::json!({ "query": "query { viewer { login } }", "variables": vars }))
+                .build()
+                .unwrap();
+            request_owner(&req)
+        };
+        assert_eq!(
+            graphql(serde_json::json!({ "owner": "rust-lang", "repo": "rust" })).as_deref(),
+            Some("rust-lang")
+        );
+        assert_eq!(
+            graphql(serde_json::json!({ "repository_owner": "rust-lang" })).as_deref(),
+            Some("rust-lang")
+        );
+        assert_eq!(graphql(serde_json::json!({ "user": "octocat" })), None);
+    }
+}
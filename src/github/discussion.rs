@@ -0,0 +1,416 @@
+//! Support for GitHub Discussions.
+//!
+//! Discussions are converted to an [`Issue`] when their webhooks are
+//! received, so that commands work the same way as on issues. The
+//! [`Issue::discussion`] field marks them, and the `Issue` methods which
+//! change anything go through the GraphQL API instead, since discussions
+//! don't have a REST API.
+
+use super::{
+    opt_string, Changes, Comment, CommentKind, GithubClient, Issue, IssueCommentAction, IssueState,
+    Label, Reaction, Repository, UnknownLabels, User,
+};
+use anyhow::Context as _;
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+
+/// An indicator used to differentiate between an issue and a discussion.
+#[derive(Debug, Clone)]
+pub struct DiscussionDetails {
+    /// The GraphQL node ID of the discussion.
+    pub node_id: String,
+}
+
+/// A discussion, as sent in the `discussion` and `discussion_comment`
+/// webhooks.
+#[derive(Debug, serde::Deserialize)]
+pub struct Discussion {
+    pub node_id: String,
+    pub number: u64,
+    pub title: String,
+    #[serde(deserialize_with = "opt_string")]
+    pub body: String,
+    pub html_url: String,
+    pub user: User,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+    pub state: DiscussionState,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscussionState {
+    Open,
+    Closed,
+    Locked,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscussionAction {
+    Created,
+    Edited,
+    Deleted,
+    Pinned,
+    Unpinned,
+    Locked,
+    Unlocked,
+    Transferred,
+    CategoryChanged,
+    Answered,
+    Unanswered,
+    Labeled,
+    Unlabeled,
+    Closed,
+    Reopened,
+    /// An action added by GitHub after this was written.
+    #[serde(other)]
+    Other,
+}
+
+/// <https://docs.github.com/en/webhooks/webhook-events-and-payloads#discussion>
+#[derive(Debug, serde::Deserialize)]
+pub struct DiscussionEvent {
+    pub action: DiscussionAction,
+    pub discussion: Discussion,
+    pub changes: Option<Changes>,
+    pub repository: Repository,
+}
+
+/// <https://docs.github.com/en/webhooks/webhook-events-and-payloads#discussion_comment>
+#[derive(Debug, serde::Deserialize)]
+pub struct DiscussionCommentEvent {
+    pub action: IssueCommentAction,
+    pub changes: Option<Changes>,
+    pub discussion: Discussion,
+    pub comment: Comment,
+    pub repository: Repository,
+}
+
+impl Discussion {
+    /// Converts the discussion to an issue in the given repository.
+    pub fn into_issue(self, repository: &Repository) -> Issue {
+        Issue {
+            number: self.number,
+            body: self.body,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            merge_commit_sha: None,
+            title: self.title,
+            html_url: self.html_url,
+            user: self.user,
+            labels: self.labels,
+            assignees: Vec::new(),
+            pull_request: None,
+            discussion: Some(DiscussionDetails {
+                node_id: self.node_id,
+            }),
+            merged: false,
+            draft: false,
+            // Only used to find the repository, discussion comments can't be
+            // posted through the REST API.
+            comments_url: format!(
+                "https://api.github.com/repos/{}/discussions/{}/comments",
+                repository.full_name, self.number
+            ),
+            repository: OnceCell::new(),
+            base: None,
+            head: None,
+            state: if self.state == DiscussionState::Open {
+                IssueState::Open
+            } else {
+                IssueState::Closed
+            },
+        }
+    }
+}
+
+/// Runs a GraphQL mutation, failing if GitHub reports any errors.
+async fn mutate(
+    client: &GithubClient,
+    query: &str,
+    vars: serde_json::Value,
+) -> anyhow::Result<serde_json::Value> {
+    let response: serde_json::Value = client.graphql_query(query, vars).await?;
+    if let Some(errors) = response["errors"].as_array() {
+        let messages: Vec<_> = errors
+            .iter()
+            .map(|err| err["message"].as_str().unwrap_or_default())
+            .collect();
+        anyhow::bail!("GraphQL mutation failed: {}", messages.join("\n"));
+    }
+    Ok(response)
+}
+
+pub(super) async fn post_comment(
+    client: &GithubClient,
+    discussion: &DiscussionDetails,
+    body: &str,
+) -> anyhow::Result<serde_json::Value> {
+    mutate(
+        client,
+        "mutation($discussion:ID!, $body:String!) {
+            addDiscussionComment(input: {discussionId: $discussion, body: $body}) {
+                comment {
+                    id
+                    databaseId
+                    body
+                    url
+                    author {
+                        login
+                    }
+                    updatedAt
+                }
+            }
+        }",
+        serde_json::json!({
+            "discussion": discussion.node_id,
+            "body": body,
+        }),
+    )
+    .await
+    .context("failed to post discussion comment")
+}
+
+pub(super) async fn create_comment(
+    client: &GithubClient,
+    discussion: &DiscussionDetails,
+    body: &str,
+) -> anyhow::Result<Comment> {
+    let response = post_comment(client, discussion, body).await?;
+    let comment = &response["data"]["addDiscussionComment"]["comment"];
+    Ok(Comment {
+        id: comment["databaseId"]
+            .as_u64()
+            .context("discussion comment has no ID")? as usize,
+        node_id: comment["id"].as_str().unwrap_or_default().to_string(),
+        body: comment["body"].as_str().unwrap_or_default().to_string(),
+        html_url: comment["url"].as_str().unwrap_or_default().to_string(),
+        user: User {
+            login: comment["author"]["login"]
+                .as_str()
+                .unwrap_or("ghost")
+                .to_string(),
+            id: None,
+        },
+        updated_at: serde_json::from_value(comment["updatedAt"].clone())
+            .context("discussion comment has no update time")?,
+        pr_review_state: None,
+        kind: CommentKind::Issue,
+    })
+}
+
+pub(super) async fn edit_body(
+    client: &GithubClient,
+    discussion: &DiscussionDetails,
+    body: &str,
+) -> anyhow::Result<()> {
+    mutate(
+        client,
+        "mutation($discussion:ID!, $body:String!) {
+            updateDiscussion(input: {discussionId: $discussion, body: $body}) {
+                clientMutationId
+            }
+        }",
+        serde_json::json!({
+            "discussion": discussion.node_id,
+            "body": body,
+        }),
+    )
+    .await
+    .context("failed to edit discussion body")?;
+    Ok(())
+}
+
+/// Adds a reaction to the discussion or discussion comment with the given
+/// node ID.
+pub(super) async fn add_reaction(
+    client: &GithubClient,
+    subject: &str,
+    reaction: Reaction,
+) -> anyhow::Result<()> {
+    mutate(
+        client,
+        "mutation($subject:ID!, $content:ReactionContent!) {
+            addReaction(input: {subjectId: $subject, content: $content}) {
+                clientMutationId
+            }
+        }",
+        serde_json::json!({
+            "subject": subject,
+            "content": reaction.graphql_name(),
+        }),
+    )
+    .await
+    .with_context(|| format!("failed to add reaction to {subject}"))?;
+    Ok(())
+}
+
+pub(super) async fn close(
+    client: &GithubClient,
+    discussion: &DiscussionDetails,
+) -> anyhow::Result<()> {
+    mutate(
+        client,
+        "mutation($discussion:ID!) {
+            closeDiscussion(input: {discussionId: $discussion}) {
+                clientMutationId
+            }
+        }",
+        serde_json::json!({
+            "discussion": discussion.node_id,
+        }),
+    )
+    .await
+    .context("failed to close discussion")?;
+    Ok(())
+}
+
+/// Returns the node IDs of the given labels, failing with [`UnknownLabels`]
+/// if any of them don't exist in the repository.
+async fn label_ids(
+    client: &GithubClient,
+    issue: &Issue,
+    labels: &[String],
+) -> anyhow::Result<Vec<String>> {
+    let mut ids = Vec::new();
+    let mut unknown_labels = Vec::new();
+    for label in labels {
+        let response: serde_json::Value = client
+            .graphql_query(
+                "query($owner:String!, $repo:String!, $label:String!) {
+                    repository(owner: $owner, name: $repo) {
+                        label(name: $label) {
+                            id
+                        }
+                    }
+                }",
+                serde_json::json!({
+                    "owner": issue.repository().organization,
+                    "repo": issue.repository().repository,
+                    "label": label,
+                }),
+            )
+            .await?;
+        match response["data"]["repository"]["label"]["id"].as_str() {
+            Some(id) => ids.push(id.to_string()),
+            None => unknown_labels.push(label.clone()),
+        }
+    }
+    if !unknown_labels.is_empty() {
+        return Err(UnknownLabels {
+            labels: unknown_labels,
+        }
+        .into());
+    }
+    Ok(ids)
+}
+
+pub(super) async fn add_labels(
+    client: &GithubClient,
+    issue: &Issue,
+    discussion: &DiscussionDetails,
+    labels: &[String],
+) -> anyhow::Result<()> {
+    let ids = label_ids(client, issue, labels).await?;
+    mutate(
+        client,
+        "mutation($discussion:ID!, $labels:[ID!]!) {
+            addLabelsToLabelable(input: {labelableId: $discussion, labelIds: $labels}) {
+                clientMutationId
+            }
+        }",
+        serde_json::json!({
+            "discussion": discussion.node_id,
+            "labels": ids,
+        }),
+    )
+    .await
+    .context("failed to add labels")?;
+    Ok(())
+}
+
+pub(super) async fn remove_label(
+    client: &GithubClient,
+    issue: &Issue,
+    discussion: &DiscussionDetails,
+    label: &str,
+) -> anyhow::Result<()> {
+    let ids = label_ids(client, issue, &[label.to_string()]).await?;
+    mutate(
+        client,
+        "mutation($discussion:ID!, $labels:[ID!]!) {
+            removeLabelsFromLabelable(input: {labelableId: $discussion, labelIds: $labels}) {
+                clientMutationId
+            }
+        }",
+        serde_json::json!({
+            "discussion": discussion.node_id,
+            "labels": ids,
+        }),
+    )
+    .await
+    .context("failed to delete label")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discussion_comment_event() {
+        let payload = serde_json::json!({
+            "action": "created",
+            "discussion": {
+                "node_id": "D_kwDOABCD",
+                "number": 42,
+                "title": "Design thread",
+                "body": null,
+                "html_url": "https://github.com/rust-lang/rfcs/discussions/42",
+                "user": { "login": "octocat", "id": 1 },
+                "labels": [{ "name": "T-lang" }],
+                "created_at": "2023-01-01T00:00:00Z",
+                "updated_at": "2023-01-02T00:00:00Z",
+                "state": "open",
+                "category": { "name": "Ideas" },
+            },
+            "comment": {
+                "id": 7,
+                "node_id": "DC_kwDOABCD",
+                "body": "@rustbot label +I-nominated",
+                "html_url": "https://github.com/rust-lang/rfcs/discussions/42#discussioncomment-7",
+                "user": { "login": "octocat", "id": 1 },
+                "created_at": "2023-01-02T00:00:00Z",
+                "updated_at": "2023-01-02T00:00:00Z",
+            },
+            "repository": {
+                "full_name": "rust-lang/rfcs",
+                "default_branch": "master",
+            },
+        });
+        let event: DiscussionCommentEvent = serde_json::from_value(payload).unwrap();
+        assert_eq!(event.comment.id, 7);
+        assert_eq!(event.comment.node_id, "DC_kwDOABCD");
+
+        let issue = event.discussion.into_issue(&event.repository);
+        assert_eq!(issue.global_id(), "rust-lang/rfcs#42");
+        assert_eq!(issue.body, "");
+        assert!(issue.is_open());
+        assert!(!issue.is_pr());
+        assert_eq!(issue.discussion.unwrap().node_id, "D_kwDOABCD");
+    }
+
+    #[test]
+    fn unknown_discussion_action() {
+        let action: DiscussionAction = serde_json::from_value(serde_json::json!("pinned")).unwrap();
+        assert_eq!(action, DiscussionAction::Pinned);
+        let action: DiscussionAction =
+            serde_json::from_value(serde_json::json!("archived")).unwrap();
+        assert_eq!(action, DiscussionAction::Other);
+    }
+}
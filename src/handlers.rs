@@ -1,9 +1,13 @@
 use crate::config::{self, Config, ConfigurationError};
 use crate::github::{Event, GithubClient, IssueCommentAction, IssuesAction, IssuesEvent};
+use crate::metrics;
 use octocrab::Octocrab;
 use parser::command::{assign::AssignCommand, Command, Input};
+use std::collections::HashSet;
 use std::fmt;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Instant;
 use tracing as log;
 
 #[derive(Debug)]
@@ -25,10 +29,13 @@ impl fmt::Display for HandlerError {
 
 mod assign;
 mod autolabel;
+pub mod check_config;
+mod ci_failure;
 mod close;
 pub mod docs_update;
 mod github_releases;
 mod glacier;
+mod help;
 pub mod jobs;
 mod major_change;
 mod mentions;
@@ -39,72 +46,84 @@ mod note;
 mod notification;
 mod notify_zulip;
 mod ping;
+mod pr_tracking;
+mod preview;
 mod prioritize;
 mod relabel;
 mod review_submitted;
 mod rfc_helper;
 pub mod rustc_commits;
 mod shortcut;
+mod validate_config;
 
-pub async fn handle(ctx: &Context, event: &Event) -> Vec<HandlerError> {
-    let config = config::get(&ctx.github, event.repo()).await;
+/// Runs the handlers for an event.
+///
+/// The handlers named in `finished` already ran for a previous attempt at the
+/// event and are skipped. The handlers which don't fail with a
+/// [`HandlerError::Other`] are added to it, so that a retry only runs the ones
+/// which failed. The exception is the notification handler, which is added
+/// before it runs, so that a retry never pings anyone twice.
+pub async fn handle(
+    ctx: &Context,
+    event: &Event,
+    finished: &mut HashSet<String>,
+) -> Vec<HandlerError> {
+    // Make sure the handlers for a push which changes the configuration
+    // already see the new version.
+    let config = match event {
+        Event::Push(push) if push.is_default_branch() && push.touches(config::CONFIG_FILE_NAME) => {
+            config::reload(&ctx.github, event.repo(), &push.after).await
+        }
+        _ => config::get(&ctx.github, event.repo()).await,
+    };
     if let Err(e) = &config {
         log::warn!("configuration error {}: {e}", event.repo().full_name);
     }
     let mut errors = Vec::new();
 
     if let (Ok(config), Event::Issue(event)) = (config.as_ref(), event) {
-        handle_issue(ctx, event, config, &mut errors).await;
+        // Discussions only support commands.
+        if event.issue.discussion.is_none() {
+            handle_issue(ctx, event, config, finished, &mut errors).await;
+        }
     }
 
     if let Some(body) = event.comment_body() {
-        handle_command(ctx, event, &config, body, &mut errors).await;
+        handle_command(ctx, event, &config, body, finished, &mut errors).await;
     }
 
-    if let Err(e) = notification::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with notification handler: {:?}",
-            event,
-            e
-        );
-    }
+    let handler = notification::handle(ctx, event);
+    run_at_most_once(finished, event, "notification", handler).await;
 
-    if let Err(e) = rustc_commits::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with rustc_commits handler: {:?}",
-            event,
-            e
-        );
-    }
+    let handler = rustc_commits::handle(ctx, event);
+    run_direct(finished, &mut errors, "rustc_commits", handler).await;
 
-    if let Err(e) = milestone_prs::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with milestone_prs handler: {:?}",
-            event,
-            e
-        );
-    }
+    let handler = milestone_prs::handle(ctx, event);
+    run_direct(finished, &mut errors, "milestone_prs", handler).await;
 
-    if let Err(e) = rfc_helper::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with rfc_helper handler: {:?}",
-            event,
-            e
-        );
-    }
+    let handler = validate_config::handle(ctx, event);
+    run_direct(finished, &mut errors, "validate_config", handler).await;
+
+    let handler = rfc_helper::handle(ctx, event);
+    run_direct(finished, &mut errors, "rfc_helper", handler).await;
 
     if let Some(config) = config
         .as_ref()
         .ok()
         .and_then(|c| c.review_submitted.as_ref())
     {
-        if let Err(e) = review_submitted::handle(ctx, event, config).await {
-            log::error!(
-                "failed to process event {:?} with review_submitted handler: {:?}",
-                event,
-                e
-            )
-        }
+        let handler = review_submitted::handle(ctx, event, config);
+        run_direct(finished, &mut errors, "review_submitted", handler).await;
+    }
+
+    if config.as_ref().is_ok_and(|c| c.assign.is_some()) {
+        let handler = pr_tracking::handle(ctx, event);
+        run_direct(finished, &mut errors, "pr_tracking", handler).await;
+    }
+
+    if let Some(ci_failure_config) = config.as_ref().ok().and_then(|c| c.ci_failure.as_ref()) {
+        let handler = ci_failure::handle(ctx, event, ci_failure_config);
+        run_direct(finished, &mut errors, "ci_failure", handler).await;
     }
 
     if let Some(ghr_config) = config
@@ -112,29 +131,85 @@ pub async fn handle(ctx: &Context, event: &Event) -> Vec<HandlerError> {
         .ok()
         .and_then(|c| c.github_releases.as_ref())
     {
-        if let Err(e) = github_releases::handle(ctx, event, ghr_config).await {
-            log::error!(
-                "failed to process event {:?} with github_releases handler: {:?}",
-                event,
-                e
-            );
-        }
+        let handler = github_releases::handle(ctx, event, ghr_config);
+        run_direct(finished, &mut errors, "github_releases", handler).await;
     }
 
     errors
 }
 
+/// Runs a handler unless it is `finished`.
+///
+/// It is only marked as finished once it succeeds; a failure is added to
+/// `errors` (and logged with them), so that the delivery is retried.
+async fn run_direct(
+    finished: &mut HashSet<String>,
+    errors: &mut Vec<HandlerError>,
+    name: &str,
+    handler: impl Future<Output = anyhow::Result<()>>,
+) {
+    if finished.contains(name) {
+        return;
+    }
+    match handler.await {
+        Ok(()) => {
+            finished.insert(name.to_string());
+        }
+        Err(e) => {
+            errors.push(HandlerError::Other(
+                e.context(format!("failed to process event with {} handler", name)),
+            ));
+        }
+    }
+}
+
+/// Runs a handler which must not run again for the same event, unless it is
+/// `finished`.
+///
+/// It is marked as finished before it runs, and its errors are only logged,
+/// since a retry could repeat whatever it did before failing.
+async fn run_at_most_once(
+    finished: &mut HashSet<String>,
+    event: &Event,
+    name: &str,
+    handler: impl Future<Output = anyhow::Result<()>>,
+) {
+    if !finished.insert(name.to_string()) {
+        return;
+    }
+    if let Err(e) = handler.await {
+        log::error!(
+            "failed to process event {:?} with {} handler: {:?}",
+            event,
+            name,
+            e
+        );
+    }
+}
+
+/// Whether none of the `errors` should cause the handler to be retried.
+fn is_finished(errors: &[HandlerError]) -> bool {
+    !errors.iter().any(|e| matches!(e, HandlerError::Other(_)))
+}
+
 macro_rules! issue_handlers {
     ($($name:ident,)*) => {
         async fn handle_issue(
             ctx: &Context,
             event: &IssuesEvent,
             config: &Arc<Config>,
+            finished: &mut HashSet<String>,
             errors: &mut Vec<HandlerError>,
         ) {
             $(
-            match $name::parse_input(ctx, event, config.$name.as_ref()).await {
-                Err(err) => errors.push(HandlerError::Message(err)),
+            let key = concat!("issue/", stringify!($name));
+            let start = Instant::now();
+            let errors_before = errors.len();
+            let handled = !finished.contains(key) && match $name::parse_input(ctx, event, config.$name.as_ref()).await {
+                Err(err) => {
+                    errors.push(HandlerError::Message(err));
+                    true
+                }
                 Ok(Some(input)) => {
                     if let Some(config) = &config.$name {
                         $name::handle_input(ctx, config, event, input).await.unwrap_or_else(|err| errors.push(HandlerError::Other(err)));
@@ -146,9 +221,17 @@ macro_rules! issue_handlers {
                             stringify!($name)
                         )));
                     }
+                    true
                 }
-                Ok(None) => {}
-            })*
+                Ok(None) => false,
+            };
+            if handled {
+                metrics::observe_handler("issue", stringify!($name), start, &errors[errors_before..]);
+                if is_finished(&errors[errors_before..]) {
+                    finished.insert(key.to_string());
+                }
+            }
+            )*
         }
     }
 }
@@ -166,6 +249,49 @@ issue_handlers! {
     notify_zulip,
 }
 
+/// Prepares to parse the commands in `body`, including the aliases and
+/// shortcuts the repository defines.
+fn command_input<'a>(ctx: &'a Context, config: Option<&'a Config>, body: &'a str) -> Input<'a> {
+    let mut input = Input::new(body, vec![&ctx.username, "triagebot"]);
+    if let Some(aliases) = config.and_then(|config| config.aliases.as_ref()) {
+        input = input.with_aliases(
+            aliases
+                .aliases
+                .iter()
+                .map(|(alias, command)| (alias.as_str(), command.as_str())),
+        );
+    }
+    if let Some(shortcut) = config.and_then(|config| config.shortcut.as_ref()) {
+        input = input.with_shortcuts(shortcut.names());
+    }
+    input
+}
+
+/// The commands which are acknowledged with a reaction, if the repository
+/// configures `[reactions]`.
+const REACTING_COMMANDS: &[&str] = &["assign", "prioritize", "relabel", "shortcut"];
+
+/// Reacts to the comment (or issue) which used the command `name`, depending
+/// on whether it worked.
+async fn react(ctx: &Context, event: &Event, config: &Config, name: &str, success: bool) {
+    let Some(reactions) = &config.reactions else {
+        return;
+    };
+    // The bot's own comments can contain commands, e.g. to instruct the
+    // user, which it doesn't act on.
+    if !REACTING_COMMANDS.contains(&name) || event.user().login == ctx.username {
+        return;
+    }
+    let reaction = if success {
+        reactions.success
+    } else {
+        reactions.failure
+    };
+    if let Err(err) = event.add_reaction(&ctx.github, reaction).await {
+        log::warn!("failed to react to the {name} command: {err:?}");
+    }
+}
+
 macro_rules! command_handlers {
     ($($name:ident: $enum:ident,)*) => {
         async fn handle_command(
@@ -173,6 +299,7 @@ macro_rules! command_handlers {
             event: &Event,
             config: &Result<Arc<Config>, ConfigurationError>,
             body: &str,
+            finished: &mut HashSet<String>,
             errors: &mut Vec<HandlerError>,
         ) {
             match event {
@@ -186,15 +313,20 @@ macro_rules! command_handlers {
                     log::debug!("skipping event, comment was {:?}", e.action);
                     return;
                 }
-                Event::Push(_) | Event::Create(_) => {
+                Event::Push(_)
+                | Event::Create(_)
+                | Event::CheckRun(_)
+                | Event::CheckSuite(_)
+                | Event::WorkflowRun(_) => {
                     log::debug!("skipping unsupported event");
                     return;
                 }
             }
 
-            let input = Input::new(&body, vec![&ctx.username, "triagebot"]);
+            let repo_config = config.as_ref().ok().map(|config| &**config);
+            let input = command_input(ctx, repo_config, &body);
             let commands = if let Some(previous) = event.comment_from() {
-                let prev_commands = Input::new(&previous, vec![&ctx.username, "triagebot"]).collect::<Vec<_>>();
+                let prev_commands = command_input(ctx, repo_config, &previous).collect::<Vec<_>>();
                 input.filter(|cmd| !prev_commands.contains(cmd)).collect::<Vec<_>>()
             } else {
                 input.collect()
@@ -202,6 +334,14 @@ macro_rules! command_handlers {
 
             log::info!("Comment parsed to {:?}", commands);
 
+            // The commands are numbered, so that a retry skips the ones which
+            // already ran.
+            let commands: Vec<_> = commands
+                .into_iter()
+                .enumerate()
+                .map(|(i, command)| (format!("command/{i}"), command))
+                .filter(|(key, _)| !finished.contains(key))
+                .collect();
             if commands.is_empty() {
                 return;
             }
@@ -211,16 +351,20 @@ macro_rules! command_handlers {
                 Err(e @ ConfigurationError::Missing) => {
                     // r? is conventionally used to mean "hey, can you review"
                     // even if the repo doesn't have a triagebot.toml. In that
-                    // case, just ignore it.
-                    if commands
-                        .iter()
-                        .all(|cmd| matches!(cmd, Command::Assign(Ok(AssignCommand::ReviewName { .. }))))
+                    // case, just ignore it. Likewise the bot may be mentioned
+                    // without meaning to use a command.
+                    if commands.iter().all(|(_, cmd)| {
+                        matches!(
+                            cmd,
+                            Command::Assign(Ok(AssignCommand::ReviewName { .. })) | Command::Unknown(_)
+                        )
+                    })
                     {
                         return;
                     }
                     return errors.push(HandlerError::Message(e.to_string()));
                 }
-                Err(e @ ConfigurationError::Toml(_)) => {
+                Err(e @ (ConfigurationError::Toml(_) | ConfigurationError::UnknownKeys(_))) => {
                     return errors.push(HandlerError::Message(e.to_string()));
                 }
                 Err(e @ ConfigurationError::Http(_)) => {
@@ -228,14 +372,62 @@ macro_rules! command_handlers {
                 }
             };
 
-            for command in commands {
+            for (key, command) in commands {
+                let command_errors_before = errors.len();
                 match command {
+                    Command::Help(Ok(command)) => {
+                        let start = Instant::now();
+                        let errors_before = errors.len();
+                        help::handle_command(ctx, config, event, command)
+                            .await
+                            .unwrap_or_else(|err| errors.push(HandlerError::Other(err)));
+                        metrics::observe_handler("command", "help", start, &errors[errors_before..]);
+                    }
+                    Command::Help(Err(err)) => {
+                        errors.push(HandlerError::Message(format!(
+                            "Parsing help command in [comment]({}) failed: {}",
+                            event.html_url().expect("has html url"),
+                            err
+                        )));
+                    }
+                    Command::Preview(Ok(command)) => {
+                        let start = Instant::now();
+                        let errors_before = errors.len();
+                        preview::handle_command(ctx, config, event, *command)
+                            .await
+                            .unwrap_or_else(|err| {
+                                errors.push(err.downcast().unwrap_or_else(HandlerError::Other))
+                            });
+                        metrics::observe_handler("command", "preview", start, &errors[errors_before..]);
+                    }
+                    Command::Preview(Err(err)) => {
+                        errors.push(HandlerError::Message(format!(
+                            "Parsing preview command in [comment]({}) failed: {}",
+                            event.html_url().expect("has html url"),
+                            err
+                        )));
+                    }
+                    Command::Unknown(command) => {
+                        errors.push(HandlerError::Message(format!(
+                            "Parsing [comment]({}) failed: {}. \
+                            Use `@{} help` to list the commands enabled in this repository.",
+                            event.html_url().expect("has html url"),
+                            command,
+                            ctx.username,
+                        )));
+                    }
                     $(
                     Command::$enum(Ok(command)) => {
+                        let start = Instant::now();
+                        let errors_before = errors.len();
                         if let Some(config) = &config.$name {
+                            // A handler can return a `HandlerError::Message`
+                            // to have it posted as an error comment.
                             $name::handle_command(ctx, config, event, command)
                                 .await
-                                .unwrap_or_else(|err| errors.push(HandlerError::Other(err)));
+                                .unwrap_or_else(|err| {
+                                    errors.push(err.downcast().unwrap_or_else(HandlerError::Other))
+                                });
                         } else {
                             errors.push(HandlerError::Message(format!(
                                 "The feature `{}` is not enabled in this repository.\n\
@@ -244,6 +436,9 @@ macro_rules! command_handlers {
                                 stringify!($name)
                             )));
                         }
+                        metrics::observe_handler("command", stringify!($name), start, &errors[errors_before..]);
+                        let success = errors.len() == errors_before;
+                        react(ctx, event, config, stringify!($name), success).await;
                     }
                     Command::$enum(Err(err)) => {
                         errors.push(HandlerError::Message(format!(
@@ -252,8 +447,12 @@ macro_rules! command_handlers {
                             event.html_url().expect("has html url"),
                             err
                         )));
+                        react(ctx, event, config, stringify!($name), false).await;
                     })*
                 }
+                if is_finished(&errors[command_errors_before..]) {
+                    finished.insert(key);
+                }
             }
         }
     }
@@ -263,7 +462,9 @@ macro_rules! command_handlers {
 //
 // This is for handlers for commands parsed by the `parser` crate.
 // Each variant of `parser::command::Command` must be in this list,
-// preceded by the module containing the coresponding `handle_command` function
+// preceded by the module containing the coresponding `handle_command` function.
+// The exceptions are `help`, which is always enabled, `preview`, which wraps
+// another command, and unknown commands.
 command_handlers! {
     assign: Assign,
     glacier: Glacier,
@@ -283,3 +484,26 @@ pub struct Context {
     pub username: String,
     pub octocrab: Octocrab,
 }
+
+impl Context {
+    /// Returns an octocrab client for repositories owned by `owner`, which
+    /// uses the GitHub App installation token if there is one and the
+    /// personal access token otherwise.
+    pub async fn octocrab_for(&self, owner: &str) -> Octocrab {
+        let Some(token) = self.github.installation_token(owner).await else {
+            return self.octocrab.clone();
+        };
+        match octocrab::OctocrabBuilder::new()
+            .personal_token(token)
+            .build()
+        {
+            Ok(octocrab) => octocrab,
+            Err(e) => {
+                log::warn!(
+                    "failed to build octocrab for {owner}, using the personal access token: {e:?}"
+                );
+                self.octocrab.clone()
+            }
+        }
+    }
+}
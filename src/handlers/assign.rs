@@ -6,6 +6,9 @@
 //! * `@rustbot claim`: Assigns to the comment author.
 //! * `@rustbot release-assignment`: Removes the commenter's assignment.
 //! * `r? @user`: Assigns to the given user (PRs only).
+//! * `@rustbot vacation until 2026-11-30`: Prevents the comment author from
+//!   being assigned PRs until the given date (included).
+//! * `@rustbot vacation end`: Ends the vacation of the comment author early.
 //!
 //! This is capable of assigning to any user, even if they do not have write
 //! access to the repo. It does this by fake-assigning the bot and adding a
@@ -16,14 +19,21 @@
 //! This also supports auto-assignment of new PRs. Based on rules in the
 //! `assign.owners` config, it will auto-select an assignee based on the files
 //! the PR modifies.
+//!
+//! When picking from several candidates, the one assigned the fewest open PRs
+//! (as tracked by `pr_tracking`) is preferred, and candidates who reached their
+//! `review_capacity` are skipped. Users listed in `users_on_vacation`, or who
+//! set themselves on vacation, are never picked.
 
 use crate::{
     config::AssignConfig,
+    db::{assigned_prs, vacations},
     github::{self, Event, Issue, IssuesAction, Selection},
-    handlers::{Context, GithubClient, IssuesEvent},
+    handlers::{pr_tracking, Context, GithubClient, HandlerError, IssuesEvent},
     interactions::EditIssueBody,
 };
 use anyhow::{bail, Context as _};
+use chrono::NaiveDate;
 use parser::command::assign::AssignCommand;
 use parser::command::{Command, Input};
 use rand::seq::IteratorRandom;
@@ -121,7 +131,8 @@ pub(super) async fn handle_input(
 ) -> anyhow::Result<()> {
     // Don't auto-assign or welcome if the user manually set the assignee when opening.
     if event.issue.assignees.is_empty() {
-        let (assignee, from_comment) = determine_assignee(ctx, event, config, &input).await?;
+        let (assignee, from_comment, explanation) =
+            determine_assignee(ctx, event, config, &input).await?;
         if assignee.as_deref() == Some("ghost") {
             // "ghost" is GitHub's placeholder account for deleted accounts.
             // It is used here as a convenient way to prevent assignment. This
@@ -152,9 +163,14 @@ pub(super) async fn handle_input(
             };
             Some(welcome)
         } else {
-            // No welcome is posted if they are not new and they used `r?` in the opening body.
+            // No welcome is posted if they are not new and they used `r?` in
+            // the opening body, except to explain who was skipped.
             None
         };
+        let welcome = match (welcome, explanation) {
+            (Some(welcome), Some(explanation)) => Some(format!("{welcome}\n\n{explanation}")),
+            (welcome, explanation) => welcome.or(explanation),
+        };
         if let Some(assignee) = assignee {
             set_assignee(&event.issue, &ctx.github, &assignee).await;
         }
@@ -264,24 +280,29 @@ async fn set_assignee(issue: &Issue, github: &GithubClient, username: &str) {
 /// Determines who to assign the PR to based on either an `r?` command, or
 /// based on which files were modified.
 ///
-/// Returns `(assignee, from_comment)` where `assignee` is who to assign to
-/// (or None if no assignee could be found). `from_comment` is a boolean
-/// indicating if the assignee came from an `r?` command (it is false if
-/// determined from the diff).
+/// Returns `(assignee, from_comment, explanation)` where `assignee` is who to
+/// assign to (or None if no assignee could be found). `from_comment` is a
+/// boolean indicating if the assignee came from an `r?` command (it is false
+/// if determined from the diff). `explanation` says which candidates were
+/// skipped, if that changed who was picked, or why nobody could be picked.
 async fn determine_assignee(
     ctx: &Context,
     event: &IssuesEvent,
     config: &AssignConfig,
     input: &AssignInput,
-) -> anyhow::Result<(Option<String>, bool)> {
+) -> anyhow::Result<(Option<String>, bool, Option<String>)> {
     let teams = crate::team_data::teams(&ctx.github).await?;
+    let availability = Availability::load(ctx, &event.issue).await;
     if let Some(name) = find_assign_command(ctx, event) {
         if is_self_assign(&name, &event.issue.user.login) {
-            return Ok((Some(name.to_string()), true));
+            return Ok((Some(name.to_string()), true, None));
         }
         // User included `r?` in the opening PR body.
-        match find_reviewer_from_names(&teams, config, &event.issue, &[name]) {
-            Ok(assignee) => return Ok((Some(assignee), true)),
+        match find_reviewer_from_names(&teams, config, &event.issue, &[name], &availability) {
+            Ok(selection) => {
+                let explanation = selection.explanation();
+                return Ok((Some(selection.reviewer), true, explanation));
+            }
             Err(e) => {
                 event
                     .issue
@@ -291,21 +312,27 @@ async fn determine_assignee(
             }
         }
     }
+    // Why the owners of the modified files couldn't be picked, reported if
+    // the fallback group doesn't work either.
+    let mut explanation = None;
     // Errors fall-through to try fallback group.
     match find_reviewers_from_diff(config, &input.git_diff) {
         Ok(candidates) if !candidates.is_empty() => {
-            match find_reviewer_from_names(&teams, config, &event.issue, &candidates) {
-                Ok(assignee) => return Ok((Some(assignee), false)),
+            match find_reviewer_from_names(&teams, config, &event.issue, &candidates, &availability)
+            {
+                Ok(selection) => {
+                    let explanation = selection.explanation();
+                    return Ok((Some(selection.reviewer), false, explanation));
+                }
                 Err(FindReviewerError::TeamNotFound(team)) => log::warn!(
                     "team {team} not found via diff from PR {}, \
                     is there maybe a misconfigured group?",
                     event.issue.global_id()
                 ),
-                // TODO: post a comment on the PR if the reviewers were filtered due to being on vacation
-                Err(
-                    e @ FindReviewerError::NoReviewer { .. }
-                    | e @ FindReviewerError::AllReviewersFiltered { .. },
-                ) => log::trace!(
+                Err(e @ FindReviewerError::AllReviewersFiltered { .. }) => {
+                    explanation = e.explanation();
+                }
+                Err(e @ FindReviewerError::NoReviewer { .. }) => log::trace!(
                     "no reviewer could be determined for PR {}: {e}",
                     event.issue.global_id()
                 ),
@@ -322,17 +349,85 @@ async fn determine_assignee(
     }
 
     if let Some(fallback) = config.adhoc_groups.get("fallback") {
-        match find_reviewer_from_names(&teams, config, &event.issue, fallback) {
-            Ok(assignee) => return Ok((Some(assignee), false)),
+        match find_reviewer_from_names(&teams, config, &event.issue, fallback, &availability) {
+            Ok(selection) => {
+                // Explain why the owners weren't picked, before why others in
+                // the fallback group weren't.
+                let explanation = [explanation, selection.explanation()]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                let explanation = (!explanation.is_empty()).then(|| explanation.join("\n\n"));
+                return Ok((Some(selection.reviewer), false, explanation));
+            }
             Err(e) => {
                 log::trace!(
                     "failed to select from fallback group for PR {}: {e}",
                     event.issue.global_id()
                 );
+                explanation = explanation.or_else(|| e.explanation());
             }
         }
     }
-    Ok((None, false))
+    Ok((None, false, explanation))
+}
+
+/// What the database records about the reviewers, as opposed to the
+/// configuration.
+#[derive(Debug, Default)]
+struct Availability {
+    /// The number of open PRs assigned to each user in the repository, keyed
+    /// by the lowercase username.
+    workloads: HashMap<String, u32>,
+    /// The lowercase usernames of the users who set themselves on vacation.
+    on_vacation: HashSet<String>,
+}
+
+impl Availability {
+    /// Loads the availability from the database. If that fails, the error is
+    /// logged and nobody is considered busy or on vacation (apart from
+    /// `users_on_vacation`), so that PRs are still assigned.
+    async fn load(ctx: &Context, issue: &Issue) -> Availability {
+        match Availability::try_load(ctx, issue).await {
+            Ok(availability) => availability,
+            Err(e) => {
+                log::error!(
+                    "failed to load reviewer availability for {}: {e:?}",
+                    issue.global_id()
+                );
+                Availability::default()
+            }
+        }
+    }
+
+    async fn try_load(ctx: &Context, issue: &Issue) -> anyhow::Result<Availability> {
+        // Without the backfill, only the PRs which had an event since the
+        // repository started being tracked would be counted.
+        if let Err(e) = pr_tracking::backfill(ctx, issue.repository()).await {
+            log::warn!(
+                "failed to backfill assigned PRs of {}: {e:?}",
+                issue.repository()
+            );
+        }
+        let db = ctx.db.get().await;
+        Ok(Availability {
+            workloads: assigned_prs::workloads(&db, &issue.repository().to_string()).await?,
+            on_vacation: vacations::users_on_vacation(&db).await?,
+        })
+    }
+
+    fn workload(&self, user: &str) -> u32 {
+        self.workloads
+            .get(&user.to_lowercase())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether the user is on vacation, either through `users_on_vacation` in
+    /// the configuration, or through `@rustbot vacation`.
+    fn is_on_vacation(&self, config: &AssignConfig, user: &str) -> bool {
+        config.is_on_vacation(user) || self.on_vacation.contains(&user.to_lowercase())
+    }
 }
 
 /// Returns a list of candidate reviewers to use based on which files were changed.
@@ -340,7 +435,10 @@ async fn determine_assignee(
 /// May return an error if the owners map is misconfigured.
 ///
 /// Beware this may return an empty list if nothing matches.
-fn find_reviewers_from_diff(config: &AssignConfig, diff: &str) -> anyhow::Result<Vec<String>> {
+pub(super) fn find_reviewers_from_diff(
+    config: &AssignConfig,
+    diff: &str,
+) -> anyhow::Result<Vec<String>> {
     // Map of `owners` path to the number of changes found in that path.
     // This weights the reviewer choice towards places where the most edits are done.
     let mut counts: HashMap<&str, u32> = HashMap::new();
@@ -362,25 +460,7 @@ fn find_reviewers_from_diff(config: &AssignConfig, diff: &str) -> anyhow::Result
             let path = line[line.find(" b/").unwrap()..]
                 .strip_prefix(" b/")
                 .unwrap();
-            // Find the longest `owners` entries that match this path.
-            let mut longest = HashMap::new();
-            for owner_pattern in config.owners.keys() {
-                let ignore = ignore::gitignore::GitignoreBuilder::new("/")
-                    .add_line(None, owner_pattern)
-                    .with_context(|| format!("owner file pattern `{owner_pattern}` is not valid"))?
-                    .build()?;
-                if ignore.matched_path_or_any_parents(path, false).is_ignore() {
-                    let owner_len = owner_pattern.split('/').count();
-                    longest.insert(owner_pattern, owner_len);
-                }
-            }
-            let max_count = longest.values().copied().max().unwrap_or(0);
-            longest_owner_patterns.extend(
-                longest
-                    .iter()
-                    .filter(|(_, count)| **count == max_count)
-                    .map(|x| *x.0),
-            );
+            longest_owner_patterns.extend(matching_owner_patterns(config, path)?);
             // Give some weight to these patterns to start. This helps with
             // files modified without any lines changed.
             for owner_pattern in &longest_owner_patterns {
@@ -414,6 +494,31 @@ fn find_reviewers_from_diff(config: &AssignConfig, diff: &str) -> anyhow::Result
     Ok(potential)
 }
 
+/// Returns the longest `owners` patterns which match `path`. There are
+/// several if patterns of the same length match.
+pub(super) fn matching_owner_patterns<'a>(
+    config: &'a AssignConfig,
+    path: &str,
+) -> anyhow::Result<Vec<&'a str>> {
+    let mut longest = HashMap::new();
+    for owner_pattern in config.owners.keys() {
+        let ignore = ignore::gitignore::GitignoreBuilder::new("/")
+            .add_line(None, owner_pattern)
+            .with_context(|| format!("owner file pattern `{owner_pattern}` is not valid"))?
+            .build()?;
+        if ignore.matched_path_or_any_parents(path, false).is_ignore() {
+            let owner_len = owner_pattern.split('/').count();
+            longest.insert(owner_pattern.as_str(), owner_len);
+        }
+    }
+    let max_count = longest.values().copied().max().unwrap_or(0);
+    Ok(longest
+        .into_iter()
+        .filter(|(_, count)| *count == max_count)
+        .map(|(pattern, _)| pattern)
+        .collect())
+}
+
 /// Handles a command posted in a comment.
 pub(super) async fn handle_command(
     ctx: &Context,
@@ -421,6 +526,172 @@ pub(super) async fn handle_command(
     event: &Event,
     cmd: AssignCommand,
 ) -> anyhow::Result<()> {
+    let issue = event.issue().unwrap();
+    match plan_command(ctx, config, event, &cmd).await? {
+        AssignAction::Ignore => Ok(()),
+        AssignAction::Vacation { until } => {
+            let db = ctx.db.get().await;
+            let user = &event.user().login;
+            let reply = match until {
+                Some(until) => {
+                    vacations::start_vacation(&db, user, until).await?;
+                    format!(
+                        "@{user}, you are on vacation until {until}, and won't be assigned PRs until then."
+                    )
+                }
+                None => {
+                    vacations::end_vacation(&db, user).await?;
+                    format!("Welcome back, @{user}! You can be assigned PRs again.")
+                }
+            };
+            issue.post_comment(&ctx.github, &reply).await
+        }
+        AssignAction::Assign {
+            username,
+            team_label,
+        } if issue.is_pr() => {
+            if let Some(t_label) = team_label {
+                if let Err(err) = issue
+                    .add_labels(&ctx.github, vec![github::Label { name: t_label }])
+                    .await
+                {
+                    if let Some(github::UnknownLabels { .. }) = err.downcast_ref() {
+                        log::warn!("Error assigning label: {}", err);
+                    } else {
+                        return Err(err);
+                    }
+                }
+            }
+            set_assignee(issue, &ctx.github, &username).await;
+            Ok(())
+        }
+        AssignAction::Assign {
+            username: to_assign,
+            ..
+        } => {
+            // Don't re-assign if aleady assigned, e.g. on comment edit
+            if issue.contain_assignee(&to_assign) {
+                log::trace!(
+                    "ignoring assign issue {} to {}, already assigned",
+                    issue.global_id(),
+                    to_assign,
+                );
+                return Ok(());
+            }
+            let e = EditIssueBody::new(&issue, "ASSIGN");
+            let data = AssignData {
+                user: Some(to_assign.clone()),
+            };
+
+            e.apply(&ctx.github, String::new(), &data).await?;
+
+            match issue.set_assignee(&ctx.github, &to_assign).await {
+                Ok(()) => return Ok(()), // we are done
+                Err(github::AssignmentError::InvalidAssignee) => {
+                    issue
+                        .set_assignee(&ctx.github, &ctx.username)
+                        .await
+                        .context("self-assignment failed")?;
+                    let cmt_body = format!(
+                        "This issue has been assigned to @{} via [this comment]({}).",
+                        to_assign,
+                        event.html_url().unwrap()
+                    );
+                    e.apply(&ctx.github, cmt_body, &data).await?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            Ok(())
+        }
+        AssignAction::Release { user } => {
+            let selection = match &user {
+                Some(user) => Selection::One(user.as_str()),
+                None => Selection::All,
+            };
+            issue.remove_assignees(&ctx.github, selection).await?;
+            let e = EditIssueBody::new(&issue, "ASSIGN");
+            e.apply(&ctx.github, String::new(), AssignData { user: None })
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+/// Describes what the command would do, for `@rustbot preview`.
+pub(super) async fn preview(
+    ctx: &Context,
+    config: &AssignConfig,
+    event: &Event,
+    cmd: &AssignCommand,
+) -> anyhow::Result<Vec<String>> {
+    let issue = event.issue().unwrap();
+    let mut actions = Vec::new();
+    match plan_command(ctx, config, event, cmd).await? {
+        AssignAction::Ignore => {}
+        AssignAction::Assign {
+            username,
+            team_label,
+        } => {
+            if let Some(t_label) = team_label {
+                actions.push(format!("Add the label `{t_label}`"));
+            }
+            if issue.contain_assignee(&username) {
+                actions.push(format!("Keep @{username} assigned"));
+            } else if matches!(cmd, AssignCommand::ReviewName { name } if name != &username) {
+                actions.push(format!(
+                    "Assign @{username}, who has the fewest assigned PRs of the eligible reviewers"
+                ));
+            } else {
+                actions.push(format!("Assign @{username}"));
+            }
+        }
+        AssignAction::Release { user: Some(user) } => {
+            actions.push(format!("Unassign @{user}"));
+        }
+        AssignAction::Release { user: None } => {
+            actions.push("Unassign everyone".to_string());
+        }
+        AssignAction::Vacation { until: Some(until) } => {
+            actions.push(format!(
+                "Set @{} on vacation until {until}, so that they aren't assigned PRs",
+                event.user().login
+            ));
+        }
+        AssignAction::Vacation { until: None } => {
+            actions.push(format!("End the vacation of @{}", event.user().login));
+        }
+    }
+    Ok(actions)
+}
+
+/// What an assignment command does, decided before anything is changed so
+/// that it can also be previewed.
+#[derive(Debug, PartialEq)]
+enum AssignAction {
+    /// The command is ignored, e.g. releasing the assignment of a PR.
+    Ignore,
+    /// Assigns the user, and on PRs adds the label of the team they were
+    /// picked from with `r?`, if any.
+    Assign {
+        username: String,
+        team_label: Option<String>,
+    },
+    /// Removes the assignment of the user, or of everyone if `None`.
+    Release { user: Option<String> },
+    /// Sets the comment author on vacation until the given date, or ends
+    /// their vacation if `None`.
+    Vacation { until: Option<NaiveDate> },
+}
+
+/// Decides what the command does, failing if the user isn't allowed to use
+/// it.
+async fn plan_command(
+    ctx: &Context,
+    config: &AssignConfig,
+    event: &Event,
+    cmd: &AssignCommand,
+) -> anyhow::Result<AssignAction> {
     let is_team_member = if let Err(_) | Ok(false) = event.user().is_team_member(&ctx.github).await
     {
         false
@@ -432,44 +703,62 @@ pub(super) async fn handle_command(
     // posts contain commands to instruct the user, not things that the bot
     // should respond to.
     if event.user().login == ctx.username.as_str() {
-        return Ok(());
+        return Ok(AssignAction::Ignore);
+    }
+
+    // Vacations apply to every repository, so they don't depend on the issue.
+    match cmd {
+        AssignCommand::Vacation { until } => {
+            let until = vacations::parse_until(until).map_err(HandlerError::Message)?;
+            return Ok(AssignAction::Vacation { until: Some(until) });
+        }
+        AssignCommand::EndVacation => {
+            let db = ctx.db.get().await;
+            if vacations::vacation_until(&db, &event.user().login)
+                .await?
+                .is_none()
+            {
+                return Err(HandlerError::Message("You are not on vacation.".to_string()).into());
+            }
+            return Ok(AssignAction::Vacation { until: None });
+        }
+        _ => {}
     }
 
     let issue = event.issue().unwrap();
     if issue.is_pr() {
         if !issue.is_open() {
-            issue
-                .post_comment(&ctx.github, "Assignment is not allowed on a closed PR.")
-                .await?;
-            return Ok(());
+            return Err(HandlerError::Message(
+                "Assignment is not allowed on a closed PR.".to_string(),
+            )
+            .into());
         }
         let username = match cmd {
             AssignCommand::Own => event.user().login.clone(),
             AssignCommand::User { username } => {
                 // Allow users on vacation to assign themselves to a PR, but not anyone else.
-                if config.is_on_vacation(&username)
+                if Availability::load(ctx, issue)
+                    .await
+                    .is_on_vacation(config, username)
                     && event.user().login.to_lowercase() != username.to_lowercase()
                 {
                     // This is a comment, so there must already be a reviewer assigned. No need to assign anyone else.
-                    issue
-                        .post_comment(&ctx.github, &on_vacation_msg(&username))
-                        .await?;
-                    return Ok(());
+                    return Err(HandlerError::Message(on_vacation_msg(username)).into());
                 }
-                username
+                username.clone()
             }
             AssignCommand::Release => {
                 log::trace!(
                     "ignoring release on PR {:?}, must always have assignee",
                     issue.global_id()
                 );
-                return Ok(());
+                return Ok(AssignAction::Ignore);
             }
             AssignCommand::ReviewName { name } => {
                 if config.owners.is_empty() {
                     // To avoid conflicts with the highfive bot while transitioning,
                     // r? is ignored if `owners` is not configured in triagebot.toml.
-                    return Ok(());
+                    return Ok(AssignAction::Ignore);
                 }
                 if matches!(
                     event,
@@ -481,51 +770,56 @@ pub(super) async fn handle_command(
                     // Don't handle r? comments on new PRs. Those will be
                     // handled by the new PR trigger (which also handles the
                     // welcome message).
-                    return Ok(());
+                    return Ok(AssignAction::Ignore);
                 }
-                if is_self_assign(&name, &event.user().login) {
+                if is_self_assign(name, &event.user().login) {
                     name.to_string()
                 } else {
                     let teams = crate::team_data::teams(&ctx.github).await?;
                     // Determine if assignee is a team. If yes, add the corresponding label
                     // team name here is without prefix 't-' (e.g. 'compiler', 'libs', etc.)
-                    if let Some(team) = teams.teams.get(&name) {
-                        let t_label = format!("t-{}", &team.name);
-                        if let Err(err) = issue
-                            .add_labels(&ctx.github, vec![github::Label { name: t_label }])
-                            .await
-                        {
-                            if let Some(github::UnknownLabels { .. }) = err.downcast_ref() {
-                                log::warn!("Error assigning label: {}", err);
-                            } else {
-                                return Err(err);
-                            }
-                        }
-                    }
-
-                    match find_reviewer_from_names(&teams, config, issue, &[name]) {
-                        Ok(assignee) => assignee,
-                        Err(e) => {
-                            issue.post_comment(&ctx.github, &e.to_string()).await?;
-                            return Ok(());
-                        }
-                    }
+                    let team_label = teams.teams.get(name).map(|team| format!("t-{}", team.name));
+                    let availability = Availability::load(ctx, issue).await;
+                    return match find_reviewer_from_names(
+                        &teams,
+                        config,
+                        issue,
+                        &[name.clone()],
+                        &availability,
+                    ) {
+                        Ok(selection) => Ok(AssignAction::Assign {
+                            username: selection.reviewer,
+                            team_label,
+                        }),
+                        Err(e) => Err(HandlerError::Message(e.to_string()).into()),
+                    };
                 }
             }
+            AssignCommand::Vacation { .. } | AssignCommand::EndVacation => {
+                unreachable!("handled above")
+            }
         };
-        set_assignee(issue, &ctx.github, &username).await;
-        return Ok(());
+        return Ok(AssignAction::Assign {
+            username,
+            team_label: None,
+        });
     }
 
     let e = EditIssueBody::new(&issue, "ASSIGN");
 
-    let to_assign = match cmd {
-        AssignCommand::Own => event.user().login.clone(),
+    match cmd {
+        AssignCommand::Own => Ok(AssignAction::Assign {
+            username: event.user().login.clone(),
+            team_label: None,
+        }),
         AssignCommand::User { username } => {
-            if !is_team_member && username != event.user().login {
+            if !is_team_member && *username != event.user().login {
                 bail!("Only Rust team members can assign other users");
             }
-            username.clone()
+            Ok(AssignAction::Assign {
+                username: username.clone(),
+                team_label: None,
+            })
         }
         AssignCommand::Release => {
             if let Some(AssignData {
@@ -533,62 +827,101 @@ pub(super) async fn handle_command(
             }) = e.current_data()
             {
                 if current == event.user().login || is_team_member {
-                    issue.remove_assignees(&ctx.github, Selection::All).await?;
-                    e.apply(&ctx.github, String::new(), AssignData { user: None })
-                        .await?;
-                    return Ok(());
+                    Ok(AssignAction::Release { user: None })
                 } else {
                     bail!("Cannot release another user's assignment");
                 }
             } else {
                 let current = &event.user().login;
                 if issue.contain_assignee(current) {
-                    issue
-                        .remove_assignees(&ctx.github, Selection::One(&current))
-                        .await?;
-                    e.apply(&ctx.github, String::new(), AssignData { user: None })
-                        .await?;
-                    return Ok(());
+                    Ok(AssignAction::Release {
+                        user: Some(current.clone()),
+                    })
                 } else {
                     bail!("Cannot release unassigned issue");
                 }
-            };
+            }
         }
         AssignCommand::ReviewName { .. } => bail!("r? is only allowed on PRs."),
-    };
-    // Don't re-assign if aleady assigned, e.g. on comment edit
-    if issue.contain_assignee(&to_assign) {
-        log::trace!(
-            "ignoring assign issue {} to {}, already assigned",
-            issue.global_id(),
-            to_assign,
-        );
-        return Ok(());
+        AssignCommand::Vacation { .. } | AssignCommand::EndVacation => {
+            unreachable!("handled above")
+        }
     }
-    let data = AssignData {
-        user: Some(to_assign.clone()),
-    };
+}
 
-    e.apply(&ctx.github, String::new(), &data).await?;
-
-    match issue.set_assignee(&ctx.github, &to_assign).await {
-        Ok(()) => return Ok(()), // we are done
-        Err(github::AssignmentError::InvalidAssignee) => {
-            issue
-                .set_assignee(&ctx.github, &ctx.username)
-                .await
-                .context("self-assignment failed")?;
-            let cmt_body = format!(
-                "This issue has been assigned to @{} via [this comment]({}).",
-                to_assign,
-                event.html_url().unwrap()
-            );
-            e.apply(&ctx.github, cmt_body, &data).await?;
+/// Why a candidate reviewer can't be assigned.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Exclusion {
+    Author,
+    AlreadyAssigned,
+    OnVacation,
+    /// The user is assigned as many open PRs as their `review_capacity`
+    /// allows.
+    AtCapacity,
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exclusion::Author => write!(f, "the PR author"),
+            Exclusion::AlreadyAssigned => write!(f, "already assigned"),
+            Exclusion::OnVacation => write!(f, "on vacation"),
+            Exclusion::AtCapacity => write!(f, "at their review capacity"),
         }
-        Err(e) => return Err(e.into()),
     }
+}
 
-    Ok(())
+/// A candidate reviewer who can't be assigned.
+#[derive(PartialEq, Eq, Debug)]
+struct Excluded {
+    name: String,
+    reason: Exclusion,
+    /// The number of open PRs assigned to the user.
+    workload: u32,
+}
+
+/// Lists the users with the reason they were excluded, e.g. "`octocat` (on
+/// vacation), `ferris` (already assigned)". This doesn't mention the users,
+/// to avoid pinging people on vacation.
+fn list_excluded<'a>(excluded: impl IntoIterator<Item = &'a Excluded>) -> String {
+    excluded
+        .into_iter()
+        .map(|excluded| format!("`{}` ({})", excluded.name, excluded.reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The reviewer picked by [`find_reviewer_from_names`].
+#[derive(PartialEq, Debug)]
+struct ReviewerSelection {
+    reviewer: String,
+    /// The number of open PRs assigned to the reviewer.
+    workload: u32,
+    /// The candidates who can't be assigned.
+    excluded: Vec<Excluded>,
+}
+
+impl ReviewerSelection {
+    /// Lists the excluded candidates who could have been picked instead of
+    /// the reviewer because they don't have more assigned PRs, if there are
+    /// any. This includes everyone when there is no workload data. The PR
+    /// author is left out, since they can't review their own PR anyway.
+    fn explanation(&self) -> Option<String> {
+        let skipped: Vec<_> = self
+            .excluded
+            .iter()
+            .filter(|excluded| {
+                excluded.reason != Exclusion::Author && excluded.workload <= self.workload
+            })
+            .collect();
+        if skipped.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "Some reviewers with no more assigned PRs were skipped: {}.",
+            list_excluded(skipped)
+        ))
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -603,15 +936,30 @@ enum FindReviewerError {
     NoReviewer { initial: Vec<String> },
     /// All potential candidates were excluded. `initial` is the list of
     /// candidate names that were used to seed the selection. `filtered` is
-    /// the users who were prevented from being assigned. One example where
-    /// this happens is if the given name was for a team where the PR author
-    /// is the only member.
+    /// the users who were prevented from being assigned, and why. One
+    /// example where this happens is if the given name was for a team where
+    /// the PR author is the only member.
     AllReviewersFiltered {
         initial: Vec<String>,
-        filtered: Vec<String>,
+        filtered: Vec<Excluded>,
     },
 }
 
+impl FindReviewerError {
+    /// Says why nobody could be picked, for the welcome message, if it is
+    /// because of the candidates rather than the configuration.
+    fn explanation(&self) -> Option<String> {
+        match self {
+            FindReviewerError::AllReviewersFiltered { initial, filtered } => Some(format!(
+                "No reviewer could be picked from `{}`: {}.",
+                initial.join(","),
+                list_excluded(filtered)
+            )),
+            FindReviewerError::TeamNotFound(_) | FindReviewerError::NoReviewer { .. } => None,
+        }
+    }
+}
+
 impl std::error::Error for FindReviewerError {}
 
 impl fmt::Display for FindReviewerError {
@@ -639,11 +987,10 @@ impl fmt::Display for FindReviewerError {
                 write!(
                     f,
                     "Could not assign reviewer from: `{}`.\n\
-                     User(s) `{}` are either the PR author, already assigned, or on vacation, \
-                     and there are no other candidates.\n\
+                     User(s) {} can't be assigned, and there are no other candidates.\n\
                      Use r? to specify someone else to assign.",
                     initial.join(","),
-                    filtered.join(","),
+                    list_excluded(filtered),
                 )
             }
         }
@@ -656,46 +1003,62 @@ impl fmt::Display for FindReviewerError {
 /// `@octocat`, or names from the owners map. It can contain GitHub usernames,
 /// auto-assign groups, or rust-lang team names. It must have at least one
 /// entry.
+///
+/// Candidates who reached their `review_capacity` are skipped, and the one
+/// with the fewest open PRs is picked, at random if several have the same
+/// number.
 fn find_reviewer_from_names(
     teams: &Teams,
     config: &AssignConfig,
     issue: &Issue,
     names: &[String],
-) -> Result<String, FindReviewerError> {
-    let candidates = candidate_reviewers_from_names(teams, config, issue, names)?;
-    // This uses a relatively primitive random choice algorithm.
-    // GitHub's CODEOWNERS supports much more sophisticated options, such as:
-    //
-    // - Round robin: Chooses reviewers based on who's received the least
-    //   recent review request, focusing on alternating between all members of
-    //   the team regardless of the number of outstanding reviews they
-    //   currently have.
-    // - Load balance: Chooses reviewers based on each member's total number
-    //   of recent review requests and considers the number of outstanding
-    //   reviews for each member. The load balance algorithm tries to ensure
-    //   that each team member reviews an equal number of pull requests in any
-    //   30 day period.
-    //
-    // Additionally, with CODEOWNERS, users marked as "Busy" in the GitHub UI
-    // will not be selected for reviewer. There are several other options for
-    // configuring CODEOWNERS as well.
-    //
-    // These are all ideas for improving the selection here. However, I'm not
-    // sure they are really worth the effort.
-    Ok(candidates
-        .into_iter()
-        .choose(&mut rand::thread_rng())
-        .expect("candidate_reviewers_from_names always returns at least one entry")
-        .to_string())
+    availability: &Availability,
+) -> Result<ReviewerSelection, FindReviewerError> {
+    let (candidates, mut excluded) =
+        candidate_reviewers_from_names(teams, config, issue, names, availability)?;
+    let workload = |name: &str| availability.workload(name);
+    let (available, mut at_capacity): (Vec<&str>, Vec<&str>) =
+        candidates.into_iter().partition(|name| {
+            !matches!(config.review_capacity(name), Some(capacity) if workload(name) >= capacity)
+        });
+    at_capacity.sort_unstable();
+    excluded.extend(at_capacity.into_iter().map(|name| Excluded {
+        name: name.to_string(),
+        reason: Exclusion::AtCapacity,
+        workload: workload(name),
+    }));
+    // GitHub's CODEOWNERS has other options which could be worth considering,
+    // such as round robin (whoever received the least recent review request),
+    // or balancing the number of review requests over the last 30 days rather
+    // than the number of open PRs.
+    let least = available.iter().map(|name| workload(name)).min();
+    match least {
+        Some(least) => Ok(ReviewerSelection {
+            reviewer: available
+                .into_iter()
+                .filter(|name| workload(name) == least)
+                .choose(&mut rand::thread_rng())
+                .expect("the least loaded candidate is available")
+                .to_string(),
+            workload: least,
+            excluded,
+        }),
+        None => Err(FindReviewerError::AllReviewersFiltered {
+            initial: names.to_vec(),
+            filtered: excluded,
+        }),
+    }
 }
 
-/// Returns a list of candidate usernames to choose as a reviewer.
+/// Returns a list of candidate usernames to choose as a reviewer, and the
+/// users who were excluded.
 fn candidate_reviewers_from_names<'a>(
     teams: &'a Teams,
     config: &'a AssignConfig,
     issue: &Issue,
     names: &'a [String],
-) -> Result<HashSet<&'a str>, FindReviewerError> {
+    availability: &Availability,
+) -> Result<(HashSet<&'a str>, Vec<Excluded>), FindReviewerError> {
     // Set of candidate usernames to choose from. This uses a set to
     // deduplicate entries so that someone in multiple teams isn't
     // over-weighted.
@@ -707,23 +1070,36 @@ fn candidate_reviewers_from_names<'a>(
     // below will pop from this and then append the expanded results of teams.
     // Usernames will be added to `candidates`.
     let mut group_expansion: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
-    // Keep track of which users get filtered out for a better error message.
-    let mut filtered = Vec::new();
+    // Keep track of which users get filtered out, and why, for a better
+    // error message.
+    let mut filtered: Vec<Excluded> = Vec::new();
     let repo = issue.repository();
     let org_prefix = format!("{}/", repo.organization);
     // Don't allow groups or teams to include the current author or assignee.
     let mut filter = |name: &&str| -> bool {
         let name_lower = name.to_lowercase();
-        let ok = name_lower != issue.user.login.to_lowercase()
-            && !config.is_on_vacation(name)
-            && !issue
-                .assignees
-                .iter()
-                .any(|assignee| name_lower == assignee.login.to_lowercase());
-        if !ok {
-            filtered.push(name.to_string());
-        }
-        ok
+        let reason = if name_lower == issue.user.login.to_lowercase() {
+            Exclusion::Author
+        } else if issue
+            .assignees
+            .iter()
+            .any(|assignee| name_lower == assignee.login.to_lowercase())
+        {
+            Exclusion::AlreadyAssigned
+        } else if availability.is_on_vacation(config, name) {
+            Exclusion::OnVacation
+        } else {
+            return true;
+        };
+        // Someone in several groups is only listed once.
+        if !filtered.iter().any(|excluded| excluded.name == *name) {
+            filtered.push(Excluded {
+                name: name.to_string(),
+                reason,
+                workload: availability.workload(name),
+            });
+        }
+        false
     };
 
     // Loop over groups to recursively expand them.
@@ -784,6 +1160,6 @@ fn candidate_reviewers_from_names<'a>(
             Err(FindReviewerError::AllReviewersFiltered { initial, filtered })
         }
     } else {
-        Ok(candidates)
+        Ok((candidates, filtered))
     }
 }
@@ -13,10 +13,10 @@ fn test_from_names(
     let (teams, config, issue) = convert_simplified(teams, config, issue);
     let names: Vec<_> = names.iter().map(|n| n.to_string()).collect();
     match (
-        candidate_reviewers_from_names(&teams, &config, &issue, &names),
+        candidate_reviewers_from_names(&teams, &config, &issue, &names, &Availability::default()),
         expected,
     ) {
-        (Ok(candidates), Ok(expected)) => {
+        (Ok((candidates, _)), Ok(expected)) => {
             let mut candidates: Vec<_> = candidates.into_iter().collect();
             candidates.sort();
             let expected: Vec<_> = expected.iter().map(|x| *x).collect();
@@ -30,6 +30,15 @@ fn test_from_names(
     }
 }
 
+/// An excluded candidate without assigned PRs.
+fn excluded(name: &str, reason: Exclusion) -> Excluded {
+    Excluded {
+        name: name.to_string(),
+        reason,
+        workload: 0,
+    }
+}
+
 /// Convert the simplified input in preparation for `candidate_reviewers_from_names`.
 fn convert_simplified(
     teams: Option<toml::Value>,
@@ -128,7 +137,7 @@ fn candidate_filtered_author_only_candidate() {
         &["compiler"],
         Err(FindReviewerError::AllReviewersFiltered {
             initial: vec!["compiler".to_string()],
-            filtered: vec!["nikomatsakis".to_string()],
+            filtered: vec![excluded("nikomatsakis", Exclusion::Author)],
         }),
     );
 }
@@ -274,15 +283,14 @@ fn vacation() {
 
     // Test that `r? user` falls through to assigning from the team.
     // See `determine_assignee` - ideally we would test that function directly instead of indirectly through `find_reviewer_from_names`.
-    let err_names = vec!["jyn514".into()];
     test_from_names(
         Some(teams.clone()),
         config.clone(),
         issue.clone(),
         &["jyn514"],
         Err(FindReviewerError::AllReviewersFiltered {
-            initial: err_names.clone(),
-            filtered: err_names,
+            initial: vec!["jyn514".into()],
+            filtered: vec![excluded("jyn514", Exclusion::OnVacation)],
         }),
     );
 
@@ -295,3 +303,160 @@ fn vacation() {
         Ok(&["Mark-Simulacrum"]),
     );
 }
+
+#[test]
+fn vacation_from_db() {
+    let teams = toml::toml!(bootstrap = ["jyn514", "Mark-Simulacrum"]);
+    let config = toml::toml!(users_on_vacation = ["jyn514"]);
+    let issue = generic_issue("octocat", "rust-lang/rust");
+    let (teams, config, issue) = convert_simplified(Some(teams), config, issue);
+    let availability = Availability {
+        on_vacation: HashSet::from(["mark-simulacrum".to_string()]),
+        ..Default::default()
+    };
+
+    // Users on vacation in either the config or the database are filtered.
+    let names = vec!["bootstrap".to_string()];
+    assert_eq!(
+        candidate_reviewers_from_names(&teams, &config, &issue, &names, &availability),
+        Err(FindReviewerError::AllReviewersFiltered {
+            initial: names.clone(),
+            filtered: vec![
+                excluded("jyn514", Exclusion::OnVacation),
+                excluded("Mark-Simulacrum", Exclusion::OnVacation),
+            ],
+        })
+    );
+}
+
+#[test]
+fn workload() {
+    let teams = toml::toml!(compiler = ["alice", "Bob", "carol"]);
+    let issue = generic_issue("octocat", "rust-lang/rust");
+    let names = vec!["compiler".to_string()];
+    let find = |config: toml::Value, workloads: &[(&str, u32)]| {
+        let (teams, config, issue) = convert_simplified(Some(teams.clone()), config, issue.clone());
+        let availability = Availability {
+            workloads: workloads
+                .iter()
+                .map(|(name, count)| (name.to_string(), *count))
+                .collect(),
+            ..Default::default()
+        };
+        find_reviewer_from_names(&teams, &config, &issue, &names, &availability)
+            .map(|selection| selection.reviewer)
+    };
+
+    // The least loaded candidate is picked, and users without assigned PRs
+    // count as having none.
+    let unlimited = toml::Value::Table(Default::default());
+    assert_eq!(
+        find(unlimited.clone(), &[("alice", 3), ("bob", 1), ("carol", 2)]),
+        Ok("Bob".to_string())
+    );
+    assert_eq!(
+        find(unlimited.clone(), &[("alice", 3), ("bob", 1)]),
+        Ok("carol".to_string())
+    );
+
+    // Users at their capacity are skipped.
+    let config = toml::toml!(
+        review_capacity = 3
+        [user_review_capacity]
+        bob = 1
+    );
+    assert_eq!(
+        find(config.clone(), &[("alice", 2), ("bob", 1), ("carol", 3)]),
+        Ok("alice".to_string())
+    );
+    assert_eq!(
+        find(config, &[("alice", 3), ("bob", 1), ("carol", 4)]),
+        Err(FindReviewerError::AllReviewersFiltered {
+            initial: names.clone(),
+            filtered: vec![
+                Excluded {
+                    workload: 1,
+                    ..excluded("Bob", Exclusion::AtCapacity)
+                },
+                Excluded {
+                    workload: 3,
+                    ..excluded("alice", Exclusion::AtCapacity)
+                },
+                Excluded {
+                    workload: 4,
+                    ..excluded("carol", Exclusion::AtCapacity)
+                },
+            ],
+        })
+    );
+}
+
+#[test]
+fn exclusion_reasons() {
+    let teams = toml::toml!(compiler = ["alice", "bob", "carol", "dave", "erin"]);
+    let config = toml::toml!(
+        users_on_vacation = ["carol"]
+        review_capacity = 2
+    );
+    let mut issue = generic_issue("alice", "rust-lang/rust");
+    issue["assignees"] = serde_json::json!([{"login": "bob", "id": 2}]);
+    let (teams, config, issue) = convert_simplified(Some(teams), config, issue);
+    let names = vec!["compiler".to_string()];
+    let availability = Availability {
+        workloads: HashMap::from([
+            ("bob".to_string(), 1),
+            ("dave".to_string(), 2),
+            ("erin".to_string(), 1),
+        ]),
+        ..Default::default()
+    };
+
+    let selection =
+        find_reviewer_from_names(&teams, &config, &issue, &names, &availability).unwrap();
+    assert_eq!(selection.reviewer, "erin");
+    let mut reasons: Vec<_> = selection
+        .excluded
+        .iter()
+        .map(|excluded| (excluded.name.as_str(), excluded.reason))
+        .collect();
+    reasons.sort_by_key(|(name, _)| *name);
+    assert_eq!(
+        reasons,
+        [
+            ("alice", Exclusion::Author),
+            ("bob", Exclusion::AlreadyAssigned),
+            ("carol", Exclusion::OnVacation),
+            ("dave", Exclusion::AtCapacity),
+        ]
+    );
+    // `bob` and `carol` have no more PRs than `erin`, the author doesn't
+    // count.
+    assert_eq!(
+        selection.explanation().as_deref(),
+        Some(
+            "Some reviewers with no more assigned PRs were skipped: \
+             `bob` (already assigned), `carol` (on vacation)."
+        )
+    );
+
+    // Without any workload data, everyone who was excluded is mentioned.
+    let availability = Availability::default();
+    let selection =
+        find_reviewer_from_names(&teams, &config, &issue, &names, &availability).unwrap();
+    assert_eq!(
+        selection.explanation().as_deref(),
+        Some(
+            "Some reviewers with no more assigned PRs were skipped: \
+             `bob` (already assigned), `carol` (on vacation)."
+        )
+    );
+
+    // Nobody is explained when the least loaded candidate is picked.
+    let availability = Availability {
+        workloads: HashMap::from([("bob".to_string(), 3), ("carol".to_string(), 3)]),
+        ..Default::default()
+    };
+    let selection =
+        find_reviewer_from_names(&teams, &config, &issue, &names, &availability).unwrap();
+    assert_eq!(selection.explanation(), None);
+}
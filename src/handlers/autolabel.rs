@@ -61,11 +61,7 @@ pub(super) async fn parse_input(
             }
 
             if let Some(files) = &files {
-                if cfg
-                    .trigger_files
-                    .iter()
-                    .any(|f| files.iter().any(|diff_file| diff_file.starts_with(f)))
-                {
+                if cfg.is_triggered_by_files(files) {
                     autolabels.push(Label {
                         name: label.to_owned(),
                     });
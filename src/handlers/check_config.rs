@@ -0,0 +1,168 @@
+//! Simulates a `triagebot.toml` locally, for the `check-config` binary.
+//!
+//! This lists the features a configuration enables, and which
+//! `assign.owners`, `autolabel` and `mentions` entries fire for a set of
+//! changed files, using the same matching as the handlers.
+
+use super::{assign, mentions};
+use crate::config::{self, Config, ConfigurationError};
+use std::fmt::Write;
+
+/// Parses a `triagebot.toml` and describes what it does for a PR which
+/// changes `files`. Fails with the error triagebot would report if the
+/// configuration is invalid.
+pub fn check(contents: &[u8], files: &[&str]) -> Result<String, ConfigurationError> {
+    let config = config::parse(None, contents)?;
+    Ok(report(&config, files))
+}
+
+fn report(config: &Config, files: &[&str]) -> String {
+    let mut out = String::from("Enabled features:\n");
+    for feature in config.enabled_features() {
+        writeln!(out, "- {feature}").unwrap();
+    }
+    if files.is_empty() {
+        return out;
+    }
+
+    if let Some(assign_config) = &config.assign {
+        out.push_str("\nReviewers from `assign.owners`:\n");
+        for file in files {
+            let reviewers = assign::find_reviewers_from_diff(assign_config, &fake_diff(&[*file]))
+                .and_then(|reviewers| {
+                    let mut patterns = assign::matching_owner_patterns(assign_config, file)?;
+                    patterns.sort();
+                    Ok((reviewers, patterns))
+                });
+            match reviewers {
+                Ok((reviewers, _)) if reviewers.is_empty() => writeln!(out, "- {file}: no owners"),
+                Ok((reviewers, patterns)) => writeln!(
+                    out,
+                    "- {file}: {} (from {})",
+                    reviewers.join(", "),
+                    patterns
+                        .iter()
+                        .map(|pattern| format!("`{pattern}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                Err(e) => writeln!(out, "- {file}: {e:#}"),
+            }
+            .unwrap();
+        }
+        if files.len() > 1 {
+            // Real PRs also weigh the owners by the number of changed lines.
+            match assign::find_reviewers_from_diff(assign_config, &fake_diff(files)) {
+                Ok(reviewers) if reviewers.is_empty() => writeln!(out, "- whole PR: no owners"),
+                Ok(reviewers) => writeln!(out, "- whole PR: {}", reviewers.join(", ")),
+                Err(e) => writeln!(out, "- whole PR: {e:#}"),
+            }
+            .unwrap();
+        }
+    }
+
+    if let Some(autolabel) = &config.autolabel {
+        let mut labels: Vec<_> = autolabel
+            .labels
+            .iter()
+            .filter(|(_, cfg)| cfg.is_triggered_by_files(files))
+            .map(|(label, _)| label.as_str())
+            .collect();
+        labels.sort();
+        out.push_str("\nLabels from `autolabel`:\n");
+        if labels.is_empty() {
+            out.push_str("- none\n");
+        }
+        for label in labels {
+            writeln!(out, "- {label}").unwrap();
+        }
+    }
+
+    if let Some(mentions_config) = &config.mentions {
+        let mut paths = mentions::matching_paths(mentions_config, files);
+        paths.sort_by_key(|(path, _)| *path);
+        out.push_str("\nMentions:\n");
+        if paths.is_empty() {
+            out.push_str("- none\n");
+        }
+        for (path, mention) in paths {
+            writeln!(out, "- {path}: cc {}", mention.cc.join(", ")).unwrap();
+        }
+    }
+    out
+}
+
+/// Creates a diff which touches the given files without changing any lines.
+fn fake_diff(files: &[&str]) -> String {
+    files
+        .iter()
+        .map(|file| format!("diff --git a/{file} b/{file}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulate_changed_files() {
+        let config = r#"
+            [relabel]
+
+            [assign.owners]
+            "/compiler" = ["compiler"]
+            "/compiler/rustc_parse" = ["petrochenkov"]
+
+            [autolabel."T-compiler"]
+            trigger_files = ["compiler"]
+
+            [autolabel."T-libs"]
+            trigger_files = ["library"]
+
+            [mentions."compiler/rustc_parse"]
+            cc = ["@nnethercote"]
+        "#;
+        let report = check(
+            config.as_bytes(),
+            &["compiler/rustc_parse/src/lib.rs", "README.md"],
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            "Enabled features:\n\
+             - relabel\n\
+             - assign\n\
+             - autolabel\n\
+             - mentions\n\
+             \n\
+             Reviewers from `assign.owners`:\n\
+             - compiler/rustc_parse/src/lib.rs: petrochenkov (from `/compiler/rustc_parse`)\n\
+             - README.md: no owners\n\
+             - whole PR: petrochenkov\n\
+             \n\
+             Labels from `autolabel`:\n\
+             - T-compiler\n\
+             \n\
+             Mentions:\n\
+             - compiler/rustc_parse: cc @nnethercote\n"
+        );
+    }
+
+    #[test]
+    fn invalid_owners_pattern() {
+        let config = r#"
+            [assign.owners]
+            "/compiler" = ["compiler"]
+            "/library/{" = ["libs"]
+        "#;
+        let report = check(config.as_bytes(), &["compiler/lib.rs", "README.md"]).unwrap();
+        // The whole PR line reports the error too, instead of being dropped.
+        let error = "owner file pattern `/library/{` is not valid";
+        for prefix in ["- compiler/lib.rs: ", "- README.md: ", "- whole PR: "] {
+            assert!(
+                report.contains(&format!("{prefix}{error}")),
+                "missing `{prefix}` line in:\n{report}"
+            );
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Purpose: When the checks of a PR fail, keep a single comment up to date
+//! which lists the failing jobs and links to their logs, and relabel the PR
+//! once the checks pass again.
+//!
+//! This is triggered by `check_run`, `check_suite` and `workflow_run` events.
+
+use crate::{
+    config::CiFailureConfig,
+    db::issue_data::{self, IssueData},
+    github::{CheckRun, CheckStatus, Event, Issue, IssueState, Label, Repository},
+    handlers::Context,
+};
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+const CI_FAILURE_KEY: &str = "ci_failure";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CiFailureState {
+    /// The comment listing the failing jobs, if one has been posted.
+    comment_id: Option<usize>,
+    /// The commit whose checks were last reported.
+    head_sha: Option<String>,
+    /// The names of the failing jobs which were last reported.
+    failing: Vec<String>,
+}
+
+pub(super) async fn handle(
+    ctx: &Context,
+    event: &Event,
+    config: &CiFailureConfig,
+) -> anyhow::Result<()> {
+    let (head_sha, pull_requests) = match event {
+        Event::CheckRun(e) if e.check_run.status == CheckStatus::Completed => {
+            (&e.check_run.head_sha, &e.check_run.pull_requests)
+        }
+        Event::CheckSuite(e) if e.check_suite.status == Some(CheckStatus::Completed) => {
+            (&e.check_suite.head_sha, &e.check_suite.pull_requests)
+        }
+        Event::WorkflowRun(e) if e.workflow_run.status == Some(CheckStatus::Completed) => {
+            (&e.workflow_run.head_sha, &e.workflow_run.pull_requests)
+        }
+        _ => return Ok(()),
+    };
+    let repo = event.repo();
+
+    let prs = if pull_requests.is_empty() {
+        // GitHub doesn't fill in the pull requests for PRs from forks.
+        repo.pulls_for_commit(&ctx.github, head_sha).await?
+    } else {
+        let mut prs = Vec::new();
+        for pr in pull_requests {
+            prs.push(repo.get_pr(&ctx.github, pr.number).await?);
+        }
+        prs
+    };
+    // Results for commits which have since been replaced are no longer
+    // interesting.
+    let prs: Vec<_> = prs
+        .into_iter()
+        .filter(|pr| {
+            pr.state == IssueState::Open
+                && pr.head.as_ref().map_or(false, |head| head.sha == *head_sha)
+        })
+        .collect();
+    for pr in &prs {
+        update_pr(ctx, config, repo, pr, head_sha).await?;
+    }
+    Ok(())
+}
+
+async fn update_pr(
+    ctx: &Context,
+    config: &CiFailureConfig,
+    repo: &Repository,
+    pr: &Issue,
+    head_sha: &str,
+) -> anyhow::Result<()> {
+    // The check runs of a commit finish in bursts, whose events are handled
+    // concurrently. Only handle one at a time per PR, and decide what to post
+    // from the state and the check runs as they are once it's our turn.
+    let mut lock_client = ctx.db.get().await;
+    let _lock = issue_data::lock(&mut lock_client, pr, CI_FAILURE_KEY).await?;
+    let pr = &repo.get_pr(&ctx.github, pr.number).await?;
+    if pr.state != IssueState::Open || !pr.head.as_ref().is_some_and(|head| head.sha == head_sha) {
+        return Ok(());
+    }
+    let runs = repo.check_runs(&ctx.github, head_sha).await?;
+    let mut failing: Vec<&CheckRun> = runs
+        .iter()
+        .filter(|run| run.conclusion.map_or(false, |c| c.is_failure()))
+        .collect();
+    failing.sort_by(|a, b| a.name.cmp(&b.name));
+    let pending = runs.iter().any(|run| run.status != CheckStatus::Completed);
+
+    // The data itself is only locked to save it, not during the requests to
+    // GitHub.
+    let state: CiFailureState = issue_data::peek(&*ctx.db.get().await, pr, CI_FAILURE_KEY).await?;
+
+    let names: Vec<String> = failing.iter().map(|run| run.name.clone()).collect();
+    let (message, from, to) = if !failing.is_empty() {
+        if state.head_sha.as_deref() == Some(head_sha) && state.failing == names {
+            // Already reported.
+            return Ok(());
+        }
+        let (from, to) = if state.failing.is_empty() {
+            (
+                config.passed_label.as_deref(),
+                config.failed_label.as_deref(),
+            )
+        } else {
+            (None, None)
+        };
+        (failure_message(head_sha, &failing), from, to)
+    } else if !pending && !state.failing.is_empty() {
+        let message = format!("All checks are passing again as of commit {head_sha}.");
+        (
+            message,
+            config.failed_label.as_deref(),
+            config.passed_label.as_deref(),
+        )
+    } else {
+        return Ok(());
+    };
+
+    let comment_id = pr
+        .upsert_comment(&ctx.github, state.comment_id, &message)
+        .await
+        .context("failed to post ci_failure comment")?;
+    move_label(ctx, pr, from, to).await?;
+
+    let mut client = ctx.db.get().await;
+    let mut state: IssueData<'_, CiFailureState> =
+        IssueData::load(&mut client, pr, CI_FAILURE_KEY).await?;
+    state.data = CiFailureState {
+        comment_id: Some(comment_id),
+        head_sha: Some(head_sha.to_string()),
+        failing: names,
+    };
+    state.save().await?;
+    Ok(())
+}
+
+/// Removes the `from` label and adds the `to` label, if they're configured.
+async fn move_label(
+    ctx: &Context,
+    pr: &Issue,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(from) = from {
+        pr.remove_label(&ctx.github, from).await?;
+    }
+    if let Some(to) = to {
+        pr.add_labels(
+            &ctx.github,
+            vec![Label {
+                name: to.to_string(),
+            }],
+        )
+        .await
+        .context("failed to set ci_failure label")?;
+    }
+    Ok(())
+}
+
+fn failure_message(head_sha: &str, failing: &[&CheckRun]) -> String {
+    let mut message = format!("The following checks failed on commit {head_sha}:\n\n");
+    for run in failing {
+        match run.details_url.as_deref().or(run.html_url.as_deref()) {
+            Some(url) => writeln!(message, "- [{}]({url})", run.name).unwrap(),
+            None => writeln!(message, "- {}", run.name).unwrap(),
+        }
+    }
+    message.push_str("\nThis comment will be updated as the checks are re-run.");
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::github::CheckConclusion;
+
+    fn run(name: &str, details_url: Option<&str>) -> CheckRun {
+        CheckRun {
+            name: name.to_string(),
+            head_sha: "abc123".to_string(),
+            status: CheckStatus::Completed,
+            conclusion: Some(CheckConclusion::Failure),
+            html_url: Some(format!("https://github.com/rust-lang/rust/runs/{name}")),
+            details_url: details_url.map(|url| url.to_string()),
+            pull_requests: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn message() {
+        let tidy = run("tidy", Some("https://example.com/logs/tidy"));
+        let linux = run("linux", None);
+        assert_eq!(
+            failure_message("abc123", &[&linux, &tidy]),
+            "The following checks failed on commit abc123:
+
+- [linux](https://github.com/rust-lang/rust/runs/linux)
+- [tidy](https://example.com/logs/tidy)
+
+This comment will be updated as the checks are re-run."
+        );
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
     handlers::Context,
 };
 use anyhow::Context as _;
-use octocrab::Page;
+use octocrab::{Octocrab, Page};
 use std::{collections::HashMap, time::Duration};
 use tracing as log;
 
@@ -37,9 +37,11 @@ pub(super) async fn handle(
     })?;
     let changelog = Changelog::parse(config.format, &content)?;
 
+    let octocrab = ctx.octocrab_for(event.repo().owner()).await;
+
     log::debug!("loading the git tags");
     let tags = load_paginated(
-        ctx,
+        &octocrab,
         &format!("repos/{}/git/matching-refs/tags", event.repo().full_name),
         |git_ref: &GitRef| {
             git_ref
@@ -53,7 +55,7 @@ pub(super) async fn handle(
 
     log::debug!("loading the existing releases");
     let releases = load_paginated(
-        ctx,
+        &octocrab,
         &format!("repos/{}/releases", event.repo().full_name),
         |release: &Release| release.tag_name.clone(),
     )
@@ -66,8 +68,7 @@ pub(super) async fn handle(
             if let Some(release) = releases.get(tag) {
                 if release.name != expected_name || release.body != expected_body {
                     log::info!("updating release {} on {}", tag, event.repo().full_name);
-                    let _: serde_json::Value = ctx
-                        .octocrab
+                    let _: serde_json::Value = octocrab
                         .patch(
                             &release.url,
                             Some(&serde_json::json!({
@@ -82,8 +83,7 @@ pub(super) async fn handle(
                 }
             } else {
                 log::info!("creating release {} on {}", tag, event.repo().full_name);
-                let e: octocrab::Result<serde_json::Value> = ctx
-                    .octocrab
+                let e: octocrab::Result<serde_json::Value> = octocrab
                     .post(
                         format!("repos/{}/releases", event.repo().full_name),
                         Some(&serde_json::json!({
@@ -135,13 +135,17 @@ async fn load_changelog(
     Ok(String::from_utf8(resp.to_vec())?)
 }
 
-async fn load_paginated<T, R, F>(ctx: &Context, url: &str, key: F) -> anyhow::Result<HashMap<R, T>>
+async fn load_paginated<T, R, F>(
+    octocrab: &Octocrab,
+    url: &str,
+    key: F,
+) -> anyhow::Result<HashMap<R, T>>
 where
     T: serde::de::DeserializeOwned,
     R: Eq + PartialEq + std::hash::Hash,
     F: Fn(&T) -> R,
 {
-    let mut current_page: Page<T> = ctx.octocrab.get::<Page<T>, _, ()>(url, None).await?;
+    let mut current_page: Page<T> = octocrab.get::<Page<T>, _, ()>(url, None).await?;
 
     let mut items = current_page
         .take_items()
@@ -149,7 +153,7 @@ where
         .map(|val| (key(&val), val))
         .collect::<HashMap<R, T>>();
 
-    while let Some(mut new_page) = ctx.octocrab.get_page::<T>(&current_page.next).await? {
+    while let Some(mut new_page) = octocrab.get_page::<T>(&current_page.next).await? {
         items.extend(
             new_page
                 .take_items()
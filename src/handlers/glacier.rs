@@ -31,9 +31,10 @@ pub(super) async fn handle_command(
     let number = event.issue().unwrap().number;
     let user = event.user();
 
-    let octocrab = &ctx.octocrab;
+    let octocrab = ctx.octocrab_for("rust-lang").await;
+    let fork_octocrab = ctx.octocrab_for("rustbot").await;
 
-    let fork = octocrab.repos("rustbot", "glacier");
+    let fork = fork_octocrab.repos("rustbot", "glacier");
     let base = octocrab.repos("rust-lang", "glacier");
 
     let master = base
@@ -0,0 +1,223 @@
+//! Purpose: Allow anyone to find out which commands are enabled in a
+//! repository.
+//!
+//! `@rustbot help` posts a comment listing the enabled commands, with their
+//! syntax and who is allowed to use them. It is always enabled.
+
+use crate::{config::Config, github::Event, handlers::Context};
+use parser::command::help::HelpCommand;
+use std::fmt::Write;
+
+const TEAM_MEMBERS: &str = "Team members";
+const BUILTIN_SHORTCUTS: [&str; 3] = ["ready", "author", "blocked"];
+const ANYONE: &str = "Anyone";
+
+pub(super) async fn handle_command(
+    ctx: &Context,
+    config: &Config,
+    event: &Event,
+    _cmd: HelpCommand,
+) -> anyhow::Result<()> {
+    let issue = event.issue().unwrap();
+    issue
+        .post_comment(&ctx.github, &help_message(config, &ctx.username))
+        .await
+}
+
+/// Returns the commands enabled by `config`, as a Markdown table.
+fn help_message(config: &Config, bot: &str) -> String {
+    // (syntax, description, who can use it)
+    let mut commands: Vec<(String, String, String)> = Vec::new();
+    let mut add = |syntax: &str, description: &str, who: &str| {
+        commands.push((
+            syntax.replace("@bot", &format!("@{bot}")),
+            description.to_string(),
+            who.to_string(),
+        ));
+    };
+
+    if let Some(relabel) = &config.relabel {
+        let who = if relabel.allow_unauthenticated.is_empty() {
+            TEAM_MEMBERS.to_string()
+        } else {
+            let labels: Vec<_> = relabel
+                .allow_unauthenticated
+                .iter()
+                .map(|label| format!("`{label}`"))
+                .collect();
+            format!("{TEAM_MEMBERS}, anyone for {}", labels.join(", "))
+        };
+        add("@bot label +<label> -<label>", "Add or remove labels", &who);
+    }
+    if config.assign.is_some() {
+        add("@bot claim", "Assign yourself", ANYONE);
+        add("@bot assign @<user>", "Assign someone else", TEAM_MEMBERS);
+        add(
+            "@bot release-assignment",
+            "Remove the assignment",
+            "The assignee and team members",
+        );
+        add("r? @<user or team>", "Pick a reviewer for a PR", ANYONE);
+        add(
+            "@bot vacation until <yyyy-mm-dd>",
+            "Don't get assigned PRs until the given date, or `vacation end` to come back early",
+            ANYONE,
+        );
+    }
+    if let Some(ping) = &config.ping {
+        let mut teams: Vec<_> = ping.team_names().map(|team| format!("`{team}`")).collect();
+        teams.sort();
+        add(
+            "@bot ping <team>",
+            &format!("Ping a team: {}", teams.join(", ")),
+            TEAM_MEMBERS,
+        );
+    }
+    if let Some(nominate) = &config.nominate {
+        let mut teams: Vec<_> = nominate
+            .teams
+            .keys()
+            .map(|team| format!("`{team}`"))
+            .collect();
+        teams.sort();
+        add(
+            "@bot nominate <team>",
+            &format!("Nominate for discussion by a team: {}", teams.join(", ")),
+            TEAM_MEMBERS,
+        );
+        add(
+            "@bot beta-nominate <team>",
+            "Nominate for a beta backport",
+            TEAM_MEMBERS,
+        );
+        add("@bot beta-accept", "Accept a beta backport", TEAM_MEMBERS);
+    }
+    if let Some(prioritize) = &config.prioritize {
+        add(
+            "@bot prioritize",
+            &format!("Request prioritization with `{}`", prioritize.label),
+            ANYONE,
+        );
+    }
+    if config.major_change.is_some() {
+        add(
+            "@bot second",
+            "Second a major change proposal",
+            TEAM_MEMBERS,
+        );
+    }
+    if config.glacier.is_some() {
+        add(
+            "@bot glacier <url>",
+            "Add the ICE reproduction at the URL to glacier",
+            TEAM_MEMBERS,
+        );
+    }
+    if let Some(shortcut) = &config.shortcut {
+        let mut custom: Vec<_> = shortcut
+            .names()
+            .filter(|name| !BUILTIN_SHORTCUTS.contains(name))
+            .collect();
+        custom.sort();
+        for name in BUILTIN_SHORTCUTS.into_iter().chain(custom) {
+            let Some(definition) = shortcut.get(name) else {
+                continue;
+            };
+            let mut description = format!("Add {}", code_list(&definition.add));
+            if !definition.remove.is_empty() {
+                write!(description, " and remove {}", code_list(&definition.remove)).unwrap();
+            }
+            if !definition.allow_issues {
+                description.push_str(" on a PR");
+            }
+            add(&format!("@bot {name}"), &description, ANYONE);
+        }
+    }
+    if config.close.is_some() {
+        add("@bot close", "Close the issue or PR", TEAM_MEMBERS);
+    }
+    if config.note.is_some() {
+        add(
+            "@bot note <title>",
+            "Link this comment from the summary in the description",
+            ANYONE,
+        );
+        add("@bot note remove <title>", "Remove a note", ANYONE);
+    }
+    if let Some(aliases) = &config.aliases {
+        let mut aliases: Vec<_> = aliases.aliases.iter().collect();
+        aliases.sort();
+        for (alias, command) in aliases {
+            add(
+                &format!("@bot {alias}"),
+                &format!("Same as `@{bot} {command}`"),
+                "As for the command",
+            );
+        }
+    }
+    if config.relabel.is_some() || config.nominate.is_some() || config.assign.is_some() {
+        add(
+            "@bot preview <command>",
+            "Show what a `label`, `nominate` or `assign` command (or `r?`) would do, without doing it",
+            "As for the command",
+        );
+    }
+    add("@bot help", "Show this list", ANYONE);
+
+    let mut message = String::from(
+        "These commands are enabled in this repository:\n\n\
+         | Command | Description | Who can use it |\n\
+         |---|---|---|\n",
+    );
+    for (syntax, description, who) in commands {
+        writeln!(message, "| `{syntax}` | {description} | {who} |").unwrap();
+    }
+    message
+}
+
+fn code_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{item}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_enabled_commands() {
+        let config = r#"
+            [relabel]
+            allow-unauthenticated = ["C-*"]
+
+            [prioritize]
+            label = "I-prioritize"
+
+            [shortcut.needs-triage]
+            add = ["needs-triage"]
+            allow-issues = true
+
+            [aliases]
+            ptal = "ready"
+        "#;
+        let config: Config = toml::from_str(config).unwrap();
+        assert_eq!(
+            help_message(&config, "rustbot"),
+            "These commands are enabled in this repository:\n\n\
+             | Command | Description | Who can use it |\n\
+             |---|---|---|\n\
+             | `@rustbot label +<label> -<label>` | Add or remove labels | Team members, anyone for `C-*` |\n\
+             | `@rustbot prioritize` | Request prioritization with `I-prioritize` | Anyone |\n\
+             | `@rustbot ready` | Add `S-waiting-on-review` and remove `S-waiting-on-author`, `S-blocked` on a PR | Anyone |\n\
+             | `@rustbot author` | Add `S-waiting-on-author` and remove `S-waiting-on-review`, `S-blocked` on a PR | Anyone |\n\
+             | `@rustbot blocked` | Add `S-blocked` and remove `S-waiting-on-review`, `S-waiting-on-author` on a PR | Anyone |\n\
+             | `@rustbot needs-triage` | Add `needs-triage` | Anyone |\n\
+             | `@rustbot ptal` | Same as `@rustbot ready` | As for the command |\n\
+             | `@rustbot preview <command>` | Show what a `label`, `nominate` or `assign` command (or `r?`) would do, without doing it | As for the command |\n\
+             | `@rustbot help` | Show this list | Anyone |\n"
+        );
+    }
+}
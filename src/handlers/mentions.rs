@@ -60,20 +60,14 @@ pub(super) async fn parse_input(
         .unwrap_or_default()
     {
         let files = files_changed(&diff);
-        let file_paths: Vec<_> = files.iter().map(|p| Path::new(p)).collect();
-        let to_mention: Vec<_> = config
-            .paths
-            .iter()
-            .filter(|(path, MentionsPathConfig { cc, .. })| {
-                let path = Path::new(path);
-                // Only mention matching paths.
-                let touches_relevant_files = file_paths.iter().any(|p| p.starts_with(path));
+        let to_mention: Vec<_> = matching_paths(config, &files)
+            .into_iter()
+            .filter(|(_, MentionsPathConfig { cc, .. })| {
                 // Don't mention if only the author is in the list.
-                let pings_non_author = match &cc[..] {
+                match &cc[..] {
                     [only_cc] => only_cc.trim_start_matches('@') != &event.issue.user.login,
                     _ => true,
-                };
-                touches_relevant_files && pings_non_author
+                }
             })
             .map(|(key, _mention)| key.to_string())
             .collect();
@@ -84,6 +78,24 @@ pub(super) async fn parse_input(
     Ok(None)
 }
 
+/// Returns the `mentions` entries whose paths contain any of the given
+/// files.
+pub(super) fn matching_paths<'a>(
+    config: &'a MentionsConfig,
+    files: &[&str],
+) -> Vec<(&'a str, &'a MentionsPathConfig)> {
+    let file_paths: Vec<_> = files.iter().map(Path::new).collect();
+    config
+        .paths
+        .iter()
+        .filter(|(path, _)| {
+            let path = Path::new(path);
+            file_paths.iter().any(|p| p.starts_with(path))
+        })
+        .map(|(path, mention)| (path.as_str(), mention))
+        .collect()
+}
+
 pub(super) async fn handle_input(
     ctx: &Context,
     config: &MentionsConfig,
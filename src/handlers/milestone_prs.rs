@@ -68,12 +68,10 @@ pub async fn handle(ctx: &Context, event: &Event) -> anyhow::Result<()> {
 async fn get_version_standalone(ctx: &Context, merge_sha: &str) -> anyhow::Result<Option<String>> {
     let resp = ctx
         .github
-        .raw()
-        .get(&format!(
+        .send_raw(ctx.github.raw().get(&format!(
             "https://raw.githubusercontent.com/rust-lang/rust/{}/src/version",
             merge_sha
-        ))
-        .send()
+        )))
         .await
         .with_context(|| format!("retrieving src/version for {}", merge_sha))?;
 
@@ -3,7 +3,7 @@
 use crate::{
     config::NominateConfig,
     github::{self, Event},
-    handlers::Context,
+    handlers::{Context, HandlerError},
     interactions::ErrorComment,
 };
 use parser::command::nominate::{NominateCommand, Style};
@@ -14,6 +14,47 @@ pub(super) async fn handle_command(
     event: &Event,
     cmd: NominateCommand,
 ) -> anyhow::Result<()> {
+    let labels_to_add = match plan(ctx, config, event, &cmd).await {
+        Ok(labels) => labels,
+        Err(msg) => {
+            let cmnt = ErrorComment::new(&event.issue().unwrap(), msg);
+            cmnt.post(&ctx.github).await?;
+            return Ok(());
+        }
+    };
+
+    event
+        .issue()
+        .unwrap()
+        .add_labels(&ctx.github, labels_to_add)
+        .await?;
+
+    Ok(())
+}
+
+/// Describes what the command would do, for `@rustbot preview`.
+pub(super) async fn preview(
+    ctx: &Context,
+    config: &NominateConfig,
+    event: &Event,
+    cmd: &NominateCommand,
+) -> anyhow::Result<Vec<String>> {
+    let labels = plan(ctx, config, event, cmd)
+        .await
+        .map_err(HandlerError::Message)?;
+    Ok(labels
+        .into_iter()
+        .map(|label| format!("Add the label `{}`", label.name))
+        .collect())
+}
+
+/// Returns the labels to add, or the reason the command can't be used.
+async fn plan(
+    ctx: &Context,
+    config: &NominateConfig,
+    event: &Event,
+    cmd: &NominateCommand,
+) -> Result<Vec<github::Label>, String> {
     let is_team_member = if let Err(_) | Ok(false) = event.user().is_team_member(&ctx.github).await
     {
         false
@@ -22,31 +63,21 @@ pub(super) async fn handle_command(
     };
 
     if !is_team_member {
-        let cmnt = ErrorComment::new(
-            &event.issue().unwrap(),
-            format!(
-                "Nominating and approving issues and pull requests is restricted to members of\
-                 the Rust teams."
-            ),
-        );
-        cmnt.post(&ctx.github).await?;
-        return Ok(());
+        return Err(format!(
+            "Nominating and approving issues and pull requests is restricted to members of\
+             the Rust teams."
+        ));
     }
 
     let issue_labels = event.issue().unwrap().labels();
     let mut labels_to_add = vec![];
     if cmd.style == Style::BetaApprove {
         if !issue_labels.iter().any(|l| l.name == "beta-nominated") {
-            let cmnt = ErrorComment::new(
-                &event.issue().unwrap(),
-                format!(
-                    "This pull request is not beta-nominated, so it cannot be approved yet.\
-                     Perhaps try to beta-nominate it by using `@{} beta-nominate <team>`?",
-                    ctx.username,
-                ),
-            );
-            cmnt.post(&ctx.github).await?;
-            return Ok(());
+            return Err(format!(
+                "This pull request is not beta-nominated, so it cannot be approved yet.\
+                 Perhaps try to beta-nominate it by using `@{} beta-nominate <team>`?",
+                ctx.username,
+            ));
         }
 
         // Add the beta-accepted label, but don't attempt to remove beta-nominated or the team
@@ -56,16 +87,11 @@ pub(super) async fn handle_command(
         });
     } else {
         if !config.teams.contains_key(&cmd.team) {
-            let cmnt = ErrorComment::new(
-                &event.issue().unwrap(),
-                format!(
-                    "This team (`{}`) cannot be nominated for via this command;\
-                     it may need to be added to `triagebot.toml` on the default branch.",
-                    cmd.team,
-                ),
-            );
-            cmnt.post(&ctx.github).await?;
-            return Ok(());
+            return Err(format!(
+                "This team (`{}`) cannot be nominated for via this command;\
+                 it may need to be added to `triagebot.toml` on the default branch.",
+                cmd.team,
+            ));
         }
 
         let label = config.teams[&cmd.team].clone();
@@ -81,11 +107,5 @@ pub(super) async fn handle_command(
         });
     }
 
-    event
-        .issue()
-        .unwrap()
-        .add_labels(&ctx.github, labels_to_add)
-        .await?;
-
-    Ok(())
+    Ok(labels_to_add)
 }
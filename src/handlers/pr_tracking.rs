@@ -0,0 +1,78 @@
+//! Purpose: Keep track of the open PRs each reviewer is assigned, so that new
+//! PRs can go to the reviewers with the fewest of them (see `assign`).
+//!
+//! This is triggered when a PR is opened, closed, reopened, assigned or
+//! unassigned, in repositories with an `[assign]` table. The PRs which were
+//! already open before are picked up by [`backfill`] when `assign` first needs
+//! the workloads of the repository.
+
+use crate::{
+    db::assigned_prs,
+    github::{Event, IssueRepository, IssuesAction},
+    handlers::Context,
+};
+use tracing as log;
+
+pub(super) async fn handle(ctx: &Context, event: &Event) -> anyhow::Result<()> {
+    let Event::Issue(event) = event else {
+        return Ok(());
+    };
+    if !event.issue.is_pr()
+        || !matches!(
+            event.action,
+            IssuesAction::Opened
+                | IssuesAction::Closed
+                | IssuesAction::Reopened
+                | IssuesAction::Assigned
+                | IssuesAction::Unassigned
+        )
+    {
+        return Ok(());
+    }
+
+    let assignees: Vec<&str> = if event.issue.is_open() {
+        event
+            .issue
+            .assignees
+            .iter()
+            .map(|assignee| assignee.login.as_str())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let mut db = ctx.db.get().await;
+    assigned_prs::set_assignees(
+        &mut db,
+        &event.issue.repository().to_string(),
+        event.issue.number,
+        &assignees,
+    )
+    .await
+}
+
+/// Records the assignees of every open PR in the repository, unless that was
+/// already done.
+pub(super) async fn backfill(ctx: &Context, repo: &IssueRepository) -> anyhow::Result<()> {
+    let repo_name = repo.to_string();
+    let mut db = ctx.db.get().await;
+    if assigned_prs::is_backfilled(&db, &repo_name).await? {
+        return Ok(());
+    }
+    let pulls = repo.open_pulls(&ctx.github).await?;
+    let prs: Vec<(u64, Vec<&str>)> = pulls
+        .iter()
+        .map(|pull| {
+            let assignees = pull
+                .assignees
+                .iter()
+                .map(|assignee| assignee.login.as_str())
+                .collect();
+            (pull.number, assignees)
+        })
+        .collect();
+    log::info!(
+        "backfilling the assignees of {} open PRs in {repo_name}",
+        prs.len()
+    );
+    assigned_prs::backfill(&mut db, &repo_name, &prs).await
+}
@@ -0,0 +1,90 @@
+//! Purpose: Allow checking what a command would do before running it.
+//!
+//! `@rustbot preview <command>` goes through the same checks and decisions as
+//! the command itself (who may set a label, which reviewer `r?` picks, and so
+//! on), and posts a comment listing the planned actions instead of performing
+//! them. The `relabel`, `nominate` and `assign` commands (including `r?`) can
+//! be previewed.
+
+use crate::{
+    config::Config,
+    github::Event,
+    handlers::{assign, nominate, relabel, Context, HandlerError},
+};
+use parser::command::Command;
+use std::fmt::Write;
+
+pub(super) async fn handle_command(
+    ctx: &Context,
+    config: &Config,
+    event: &Event,
+    cmd: Command<'_>,
+) -> anyhow::Result<()> {
+    let plan = match (&cmd, config) {
+        (
+            Command::Relabel(Ok(cmd)),
+            Config {
+                relabel: Some(config),
+                ..
+            },
+        ) => relabel::preview(ctx, config, event, cmd).await,
+        (
+            Command::Nominate(Ok(cmd)),
+            Config {
+                nominate: Some(config),
+                ..
+            },
+        ) => nominate::preview(ctx, config, event, cmd).await,
+        (
+            Command::Assign(Ok(cmd)),
+            Config {
+                assign: Some(config),
+                ..
+            },
+        ) => assign::preview(ctx, config, event, cmd).await,
+        (Command::Relabel(Ok(_)) | Command::Nominate(Ok(_)) | Command::Assign(Ok(_)), _) => {
+            return Err(HandlerError::Message(
+                "The previewed command is not enabled in this repository.".to_string(),
+            )
+            .into());
+        }
+        (
+            Command::Relabel(Err(err)) | Command::Nominate(Err(err)) | Command::Assign(Err(err)),
+            _,
+        ) => {
+            return Err(HandlerError::Message(format!(
+                "Parsing the previewed command failed: {err}"
+            ))
+            .into());
+        }
+        _ => {
+            return Err(HandlerError::Message(
+                "Only the `label`, `nominate` and `assign` commands (including `r?`) \
+                 can be previewed."
+                    .to_string(),
+            )
+            .into());
+        }
+    };
+
+    let url = event.html_url().expect("has html url");
+    let message = match plan {
+        Ok(actions) if actions.is_empty() => format!("[This command]({url}) would do nothing."),
+        Ok(actions) => {
+            let mut message = format!("[This command]({url}) would:\n\n");
+            for action in actions {
+                writeln!(message, "- {action}").unwrap();
+            }
+            message.push_str("\nNothing has been changed.");
+            message
+        }
+        // The message of a `HandlerError::Message`, or of an error the
+        // command reports by failing.
+        Err(err) => format!("[This command]({url}) would fail: {err}"),
+    };
+    event
+        .issue()
+        .unwrap()
+        .post_comment(&ctx.github, &message)
+        .await
+}
@@ -3,16 +3,19 @@
 //! Labels are checked against the labels in the project; the bot does not support creating new
 //! labels.
 //!
+//! A label to remove can be a glob pattern (`-T-*`), which removes the matching labels the issue
+//! currently has, and `=T-lang` adds `T-lang` while removing the other `T-` labels. Each resulting
+//! label goes through the same `allow_unauthenticated` filtering as a literal one.
+//!
 //! Parsing is done in the `parser::command::relabel` module.
 //!
-//! If the command was successful, there will be no feedback beyond the label change to reduce
-//! notification noise.
+//! If the command was successful, there will be no feedback beyond the label change (and a
+//! reaction, if `[reactions]` is configured) to reduce notification noise.
 
 use crate::{
     config::RelabelConfig,
     github::{self, Event, GithubClient},
-    handlers::Context,
-    interactions::ErrorComment,
+    handlers::{Context, HandlerError},
 };
 use parser::command::relabel::{LabelDelta, RelabelCommand};
 
@@ -22,42 +25,7 @@ pub(super) async fn handle_command(
     event: &Event,
     input: RelabelCommand,
 ) -> anyhow::Result<()> {
-    let mut results = vec![];
-    let mut to_add = vec![];
-    for delta in &input.0 {
-        let name = delta.label().as_str();
-        let err = match check_filter(name, config, is_member(&event.user(), &ctx.github).await) {
-            Ok(CheckFilterResult::Allow) => None,
-            Ok(CheckFilterResult::Deny) => Some(format!(
-                "Label {} can only be set by Rust team members",
-                name
-            )),
-            Ok(CheckFilterResult::DenyUnknown) => Some(format!(
-                "Label {} can only be set by Rust team members;\
-                 we were unable to check if you are a team member.",
-                name
-            )),
-            Err(err) => Some(err),
-        };
-        if let Some(msg) = err {
-            let cmnt = ErrorComment::new(&event.issue().unwrap(), msg);
-            cmnt.post(&ctx.github).await?;
-            return Ok(());
-        }
-        match delta {
-            LabelDelta::Add(label) => {
-                to_add.push(github::Label {
-                    name: label.to_string(),
-                });
-            }
-            LabelDelta::Remove(label) => {
-                results.push((
-                    label,
-                    event.issue().unwrap().remove_label(&ctx.github, &label),
-                ));
-            }
-        }
-    }
+    let (to_add, to_remove) = plan(ctx, config, event, &input).await?;
 
     if let Err(e) = event
         .issue()
@@ -74,8 +42,13 @@ pub(super) async fn handle_command(
         return Err(e);
     }
 
-    for (label, res) in results {
-        if let Err(e) = res.await {
+    for label in to_remove {
+        if let Err(e) = event
+            .issue()
+            .unwrap()
+            .remove_label(&ctx.github, &label)
+            .await
+        {
             tracing::error!(
                 "failed to remove {:?} from issue {}: {:?}",
                 label,
@@ -89,7 +62,137 @@ pub(super) async fn handle_command(
     Ok(())
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Describes what the command would do, for `@rustbot preview`.
+pub(super) async fn preview(
+    ctx: &Context,
+    config: &RelabelConfig,
+    event: &Event,
+    input: &RelabelCommand,
+) -> anyhow::Result<Vec<String>> {
+    let (to_add, to_remove) = plan(ctx, config, event, input).await?;
+    let issue = event.issue().unwrap();
+    let mut actions = Vec::new();
+    for label in &to_add {
+        if issue.labels().contains(label) {
+            actions.push(format!(
+                "Keep the label `{}`, which is already set",
+                label.name
+            ));
+        } else if issue.discussion.is_none()
+            && !issue
+                .repository()
+                .has_label(&ctx.github, &label.name)
+                .await?
+        {
+            actions.push(format!(
+                "Fail to add the label `{}`, which doesn't exist in this repository",
+                label.name
+            ));
+        } else {
+            actions.push(format!("Add the label `{}`", label.name));
+        }
+    }
+    for label in to_remove {
+        if issue.labels().iter().any(|l| l.name == label) {
+            actions.push(format!("Remove the label `{label}`"));
+        } else {
+            actions.push(format!(
+                "Skip removing the label `{label}`, which isn't set"
+            ));
+        }
+    }
+    Ok(actions)
+}
+
+/// Returns the labels to add and remove, after checking that the user is
+/// allowed to change them.
+async fn plan(
+    ctx: &Context,
+    config: &RelabelConfig,
+    event: &Event,
+    input: &RelabelCommand,
+) -> anyhow::Result<(Vec<github::Label>, Vec<String>)> {
+    let issue = event.issue().unwrap();
+    let (to_add, to_remove) = resolve(&input.0, issue.labels()).map_err(HandlerError::Message)?;
+    let membership = is_member(&event.user(), &ctx.github).await;
+    for name in to_add.iter().chain(&to_remove) {
+        let err = match check_filter(name, config, membership) {
+            Ok(CheckFilterResult::Allow) => None,
+            Ok(CheckFilterResult::Deny) => Some(format!(
+                "Label {} can only be set by Rust team members",
+                name
+            )),
+            Ok(CheckFilterResult::DenyUnknown) => Some(format!(
+                "Label {} can only be set by Rust team members;\
+                 we were unable to check if you are a team member.",
+                name
+            )),
+            Err(err) => Some(err),
+        };
+        if let Some(msg) = err {
+            return Err(HandlerError::Message(msg).into());
+        }
+    }
+    let to_add = to_add
+        .into_iter()
+        .map(|name| github::Label { name })
+        .collect();
+    Ok((to_add, to_remove))
+}
+
+/// Resolves the patterns and `=` replacements of the command against the
+/// current labels of the issue, returning the names of the labels to add and
+/// remove.
+///
+/// Labels matched by a pattern or a family are kept if the command also adds
+/// them, so `=T-lang` on an issue labeled `T-lang` doesn't remove it.
+fn resolve(
+    deltas: &[LabelDelta],
+    current: &[github::Label],
+) -> Result<(Vec<String>, Vec<String>), String> {
+    let matchopts = glob::MatchOptions {
+        case_sensitive: false,
+        ..Default::default()
+    };
+
+    let mut to_add = vec![];
+    let mut to_remove = vec![];
+    let mut matched = vec![];
+    for delta in deltas {
+        let pattern = match delta {
+            LabelDelta::Add(label) => {
+                to_add.push(label.to_string());
+                continue;
+            }
+            LabelDelta::Remove(label) if !label.is_pattern() => {
+                to_remove.push(label.to_string());
+                continue;
+            }
+            LabelDelta::Remove(label) => label.to_string(),
+            LabelDelta::Replace(label) => {
+                to_add.push(label.to_string());
+                // The parser only accepts `=` for labels with a family.
+                format!("{}*", glob::Pattern::escape(label.family().unwrap()))
+            }
+        };
+        let glob = glob::Pattern::new(&pattern)
+            .map_err(|err| format!("Invalid label pattern `{}`: {}", pattern, err))?;
+        matched.extend(
+            current
+                .iter()
+                .filter(|l| glob.matches_with(&l.name, matchopts))
+                .map(|l| l.name.clone()),
+        );
+    }
+    for name in matched {
+        if !to_add.iter().any(|l| l.eq_ignore_ascii_case(&name)) && !to_remove.contains(&name) {
+            to_remove.push(name);
+        }
+    }
+    Ok((to_add, to_remove))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum TeamMembership {
     Member,
     Outsider,
@@ -175,9 +278,11 @@ fn match_pattern(pattern: &str, label: &str) -> anyhow::Result<MatchPatternResul
 #[cfg(test)]
 mod tests {
     use super::{
-        check_filter, match_pattern, CheckFilterResult, MatchPatternResult, TeamMembership,
+        check_filter, match_pattern, resolve, CheckFilterResult, MatchPatternResult, TeamMembership,
     };
     use crate::config::RelabelConfig;
+    use crate::github::Label;
+    use parser::command::{relabel::RelabelCommand, Command, Input};
 
     #[test]
     fn test_match_pattern() -> anyhow::Result<()> {
@@ -242,4 +347,49 @@ mod tests {
         }
         Ok(())
     }
+
+    fn resolve_command(
+        command: &str,
+        current: &[&str],
+    ) -> Result<(Vec<String>, Vec<String>), String> {
+        let current: Vec<_> = current
+            .iter()
+            .map(|name| Label {
+                name: name.to_string(),
+            })
+            .collect();
+        let text = format!("@bot {command}");
+        let deltas = match Input::new(&text, vec!["bot"]).next() {
+            Some(Command::Relabel(Ok(RelabelCommand(deltas)))) => deltas,
+            cmd => panic!("not a relabel command: {:?}", cmd),
+        };
+        resolve(&deltas, &current)
+    }
+
+    #[test]
+    fn test_resolve() {
+        let current = ["T-compiler", "t-libs", "S-waiting-on-review", "A-lint"];
+        assert_eq!(
+            resolve_command("label -T-*", &current),
+            Ok((vec![], vec!["T-compiler".into(), "t-libs".into()]))
+        );
+        assert_eq!(
+            resolve_command("label -T-* +T-lang", &current),
+            Ok((
+                vec!["T-lang".into()],
+                vec!["T-compiler".into(), "t-libs".into()]
+            ))
+        );
+        assert_eq!(
+            resolve_command("label =T-compiler -A-lint", &current),
+            Ok((
+                vec!["T-compiler".into()],
+                vec!["A-lint".into(), "t-libs".into()]
+            ))
+        );
+        assert_eq!(
+            resolve_command("label -I-* +S-blocked -S-waiting-on-review", &current),
+            Ok((vec!["S-blocked".into()], vec!["S-waiting-on-review".into()]))
+        );
+    }
 }
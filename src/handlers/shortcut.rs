@@ -1,56 +1,57 @@
 //! Purpose: Allow the use of single words shortcut to do specific actions on GitHub via comments.
 //!
-//! Parsing is done in the `parser::command::shortcut` module.
+//! Parsing is done in the `parser::command::shortcut` module. The labels each
+//! shortcut adds and removes can be configured in `[shortcut]`.
 
 use crate::{
     config::ShortcutConfig,
     github::{Event, Label},
-    handlers::Context,
-    interactions::ErrorComment,
+    handlers::{Context, HandlerError},
 };
 use parser::command::shortcut::ShortcutCommand;
 
 pub(super) async fn handle_command(
     ctx: &Context,
-    _config: &ShortcutConfig,
+    config: &ShortcutConfig,
     event: &Event,
     input: ShortcutCommand,
 ) -> anyhow::Result<()> {
     let issue = event.issue().unwrap();
-    // NOTE: if shortcuts available to issues are created, they need to be allowed here
-    if !issue.is_pr() {
-        let msg = format!("The \"{:?}\" shortcut only works on pull requests.", input);
-        let cmnt = ErrorComment::new(&issue, msg);
-        cmnt.post(&ctx.github).await?;
-        return Ok(());
+    let Some(shortcut) = config.get(input.name()) else {
+        let msg = format!(
+            "The \"{}\" shortcut is not defined in this repository.",
+            input.name()
+        );
+        return Err(HandlerError::Message(msg).into());
+    };
+    if !issue.is_pr() && !shortcut.allow_issues {
+        let msg = format!(
+            "The \"{}\" shortcut only works on pull requests.",
+            input.name()
+        );
+        return Err(HandlerError::Message(msg).into());
     }
 
     let issue_labels = issue.labels();
-    let waiting_on_review = "S-waiting-on-review";
-    let waiting_on_author = "S-waiting-on-author";
-    let blocked = "S-blocked";
-    let status_labels = [waiting_on_review, waiting_on_author, blocked];
-
-    let add = match input {
-        ShortcutCommand::Ready => waiting_on_review,
-        ShortcutCommand::Author => waiting_on_author,
-        ShortcutCommand::Blocked => blocked,
-    };
+    let has_label = |name: &str| issue_labels.iter().any(|l| l.name == name);
+    let to_add: Vec<Label> = shortcut
+        .add
+        .iter()
+        .filter(|label| !has_label(label))
+        .map(|label| Label {
+            name: label.clone(),
+        })
+        .collect();
 
-    if !issue_labels.iter().any(|l| l.name == add) {
-        for remove in status_labels {
-            if remove != add {
+    if !to_add.is_empty() {
+        // The labels to remove are mutually exclusive with the ones added,
+        // e.g. the other status labels.
+        for remove in &shortcut.remove {
+            if has_label(remove) && !shortcut.add.contains(remove) {
                 issue.remove_label(&ctx.github, remove).await?;
             }
         }
-        issue
-            .add_labels(
-                &ctx.github,
-                vec![Label {
-                    name: add.to_owned(),
-                }],
-            )
-            .await?;
+        issue.add_labels(&ctx.github, to_add).await?;
     }
 
     Ok(())
@@ -0,0 +1,694 @@
+//! Purpose: When a PR changes `triagebot.toml`, check that the new version is
+//! valid before it is merged.
+//!
+//! This fetches the `triagebot.toml` from the head of the PR, parses it, and
+//! checks that the labels, teams, ad-hoc groups, users and Zulip streams it
+//! refers to exist and that the `owners` patterns are valid. The problems are
+//! listed in a single comment, which is updated on every push.
+//!
+//! This runs whether or not the repository has a (valid) configuration,
+//! since it is most useful when adding or fixing one.
+
+use crate::{
+    config::{self, AssignConfig, Config, ConfigurationError, CONFIG_FILE_NAME},
+    db::issue_data::{self, IssueData},
+    github::{Event, Issue, IssueRepository, IssuesAction, Repository},
+    handlers::Context,
+    zulip::BOT_EMAIL,
+};
+use anyhow::Context as _;
+use parser::command::{is_command_name, shortcut::ShortcutCommand, Command, Input};
+use reqwest::StatusCode;
+use rust_team_data::v1::Teams;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+const VALIDATE_CONFIG_KEY: &str = "validate_config";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ValidateConfigState {
+    /// The comment listing the problems, if one has been posted.
+    comment_id: Option<usize>,
+    /// The problems which were last reported.
+    problems: Vec<String>,
+}
+
+pub(super) async fn handle(ctx: &Context, event: &Event) -> anyhow::Result<()> {
+    let Event::Issue(event) = event else {
+        return Ok(());
+    };
+    if !event.issue.is_pr()
+        || !matches!(
+            event.action,
+            IssuesAction::Opened | IssuesAction::Reopened | IssuesAction::Synchronize
+        )
+    {
+        return Ok(());
+    }
+    let Some(head) = &event.issue.head else {
+        return Ok(());
+    };
+    let files = event.issue.files(&ctx.github).await?;
+    if !files.iter().any(|file| file.filename == CONFIG_FILE_NAME) {
+        return Ok(());
+    }
+
+    let problems = match ctx
+        .github
+        .raw_file(&head.repo.full_name, &head.sha, CONFIG_FILE_NAME)
+        .await?
+    {
+        Some(contents) => {
+            let contents = String::from_utf8_lossy(&contents);
+            let base = config::get_base_config(&ctx.github, &event.repository).await?;
+            let base = base.as_ref().map(|(_, base)| &base[..]);
+            match parse(base, &contents) {
+                Ok(config) => {
+                    let locations = Locations::new(&contents);
+                    check(ctx, event.issue.repository(), &config, &locations).await?
+                }
+                Err(problems) => problems,
+            }
+        }
+        // The file was removed.
+        None => Vec::new(),
+    };
+    report(ctx, &event.repository, &event.issue, &head.sha, problems).await
+}
+
+/// Parses the configuration the same way as `config::get`.
+fn parse(base: Option<&[u8]>, contents: &str) -> Result<Config, Vec<String>> {
+    config::parse(base, contents.as_bytes()).map_err(|e| match e {
+        ConfigurationError::UnknownKeys(keys) => {
+            let locations = Locations::new(contents);
+            keys.iter()
+                .map(|key| located(locations.key(&key.path), key.to_string()))
+                .collect()
+        }
+        // The error includes the line and column.
+        ConfigurationError::Toml(e) => {
+            vec![format!("`{CONFIG_FILE_NAME}` could not be parsed: {e}")]
+        }
+        e => vec![e.to_string()],
+    })
+}
+
+/// Checks that everything the configuration refers to exists.
+async fn check(
+    ctx: &Context,
+    repo: &IssueRepository,
+    config: &Config,
+    locations: &Locations,
+) -> anyhow::Result<Vec<String>> {
+    let mut problems = Vec::new();
+
+    for label in referenced_labels(config) {
+        if !repo.has_label(&ctx.github, label).await? {
+            problems.push(located(
+                locations.find(label),
+                format!("the label `{label}` does not exist in {repo}"),
+            ));
+        }
+    }
+
+    let teams = crate::team_data::teams(&ctx.github).await?;
+    let mut team_names = BTreeSet::new();
+    if let Some(ping) = &config.ping {
+        team_names.extend(ping.team_names());
+    }
+    if let Some(nominate) = &config.nominate {
+        team_names.extend(nominate.teams.keys().map(|team| team.as_str()));
+    }
+    for team in team_names {
+        if !teams.teams.contains_key(team) {
+            problems.push(located(
+                locations.find(team),
+                format!("the team `{team}` does not exist in the team repository"),
+            ));
+        }
+    }
+
+    if let Some(assign) = &config.assign {
+        problems.extend(owner_pattern_problems(assign, locations));
+        for name in unresolved_reviewers(assign, &teams, repo) {
+            let problem = if name.contains('/') {
+                Some(format!(
+                    "`{name}` is not an ad-hoc group or a team in the team repository"
+                ))
+            } else if ctx.github.user_object_id(name).await?.is_none() {
+                Some(format!(
+                    "`{name}` is not a GitHub user, an ad-hoc group or a team in the team repository"
+                ))
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                problems.push(located(locations.find(name), problem));
+            }
+        }
+    }
+
+    problems.extend(alias_problems(config, locations));
+
+    let mut streams = BTreeSet::new();
+    if let Some(notify_zulip) = &config.notify_zulip {
+        streams.extend(notify_zulip.labels.values().map(|label| label.zulip_stream));
+    }
+    if let Some(major_change) = &config.major_change {
+        streams.insert(major_change.zulip_stream);
+    }
+    for stream in streams {
+        if zulip_stream_exists(ctx, stream).await? == Some(false) {
+            problems.push(located(
+                locations.find(&stream.to_string()),
+                format!("the Zulip stream {stream} does not exist"),
+            ));
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Returns the labels which are added or checked by the configuration.
+fn referenced_labels(config: &Config) -> BTreeSet<&str> {
+    let mut labels = BTreeSet::new();
+    if let Some(ping) = &config.ping {
+        labels.extend(ping.labels());
+    }
+    if let Some(nominate) = &config.nominate {
+        labels.extend(nominate.teams.values().map(|label| label.as_str()));
+    }
+    if let Some(prioritize) = &config.prioritize {
+        labels.insert(prioritize.label.as_str());
+    }
+    if let Some(major_change) = &config.major_change {
+        labels.extend([
+            major_change.enabling_label.as_str(),
+            major_change.second_label.as_str(),
+            major_change.accept_label.as_str(),
+            major_change.meeting_label.as_str(),
+        ]);
+    }
+    if let Some(autolabel) = &config.autolabel {
+        for (label, cfg) in &autolabel.labels {
+            labels.insert(label.as_str());
+            labels.extend(cfg.trigger_labels.iter().map(|label| label.as_str()));
+            labels.extend(cfg.exclude_labels.iter().map(|label| label.as_str()));
+        }
+    }
+    if let Some(notify_zulip) = &config.notify_zulip {
+        for (label, cfg) in &notify_zulip.labels {
+            labels.insert(label.as_str());
+            labels.extend(cfg.required_labels.iter().map(|label| label.as_str()));
+        }
+    }
+    if let Some(review_submitted) = &config.review_submitted {
+        labels.insert(review_submitted.reviewed_label.as_str());
+        labels.extend(review_submitted.review_labels.iter().map(|l| l.as_str()));
+    }
+    if let Some(no_merges) = &config.no_merges {
+        labels.extend(no_merges.labels.iter().map(|label| label.as_str()));
+        labels.extend(no_merges.exclude_labels.iter().map(|label| label.as_str()));
+    }
+    if let Some(ci_failure) = &config.ci_failure {
+        labels.extend(ci_failure.failed_label.as_deref());
+        labels.extend(ci_failure.passed_label.as_deref());
+    }
+    if let Some(shortcut) = &config.shortcut {
+        for definition in shortcut.shortcuts.values() {
+            labels.extend(definition.add.iter().map(|label| label.as_str()));
+            labels.extend(definition.remove.iter().map(|label| label.as_str()));
+        }
+    }
+    // Patterns (as in `relabel.allow-unauthenticated`) can't be checked.
+    labels.retain(|label| !label.contains('*'));
+    labels
+}
+
+/// Checks that each alias expands to a valid command, and that no alias or
+/// shortcut is named like a built-in command, which would be ignored.
+fn alias_problems(config: &Config, locations: &Locations) -> Vec<String> {
+    let mut shortcuts: Vec<&str> = config
+        .shortcut
+        .as_ref()
+        .map(|shortcut| shortcut.names().collect())
+        .unwrap_or_default();
+    shortcuts.sort();
+    let mut problems = Vec::new();
+    for name in &shortcuts {
+        if is_command_name(name) && ShortcutCommand::builtin(name).is_none() {
+            problems.push(located(
+                locations.key(&format!("shortcut.{name}")),
+                format!("the shortcut `{name}` is ignored, since it is a built-in command"),
+            ));
+        }
+    }
+    let mut aliases: Vec<_> = config
+        .aliases
+        .iter()
+        .flat_map(|aliases| &aliases.aliases)
+        .collect();
+    aliases.sort();
+    for (alias, command) in aliases {
+        if is_command_name(alias) {
+            problems.push(located(
+                locations.key(&format!("aliases.{alias}")),
+                format!("the alias `{alias}` is ignored, since it is a built-in command"),
+            ));
+            continue;
+        }
+        let text = format!("@triagebot {command}");
+        let parsed = Input::new(&text, vec!["triagebot"])
+            .with_shortcuts(shortcuts.iter().copied())
+            .next();
+        let problem = match parsed {
+            Some(parsed) if parsed.is_ok() => continue,
+            None | Some(Command::Unknown(_)) => {
+                format!("the alias `{alias}` doesn't expand to a command")
+            }
+            Some(_) => format!("the alias `{alias}` expands to an invalid command `{command}`"),
+        };
+        problems.push(located(locations.key(&format!("aliases.{alias}")), problem));
+    }
+    problems
+}
+
+/// Checks that the `owners` keys are valid gitignore-style patterns.
+fn owner_pattern_problems(config: &AssignConfig, locations: &Locations) -> Vec<String> {
+    let mut patterns: Vec<_> = config.owners.keys().collect();
+    patterns.sort();
+    patterns
+        .into_iter()
+        .filter_map(|pattern| {
+            let e = ignore::gitignore::GitignoreBuilder::new("/")
+                .add_line(None, pattern)
+                .err()?;
+            Some(located(
+                locations.key(&format!("assign.owners.{pattern}")),
+                format!("the owners pattern `{pattern}` is not valid: {e}"),
+            ))
+        })
+        .collect()
+}
+
+/// Returns the names in `owners` and `adhoc_groups` which aren't ad-hoc
+/// groups or teams, so must be GitHub users.
+///
+/// This resolves names the same way as `assign::candidate_reviewers_from_names`.
+fn unresolved_reviewers<'a>(
+    config: &'a AssignConfig,
+    teams: &Teams,
+    repo: &IssueRepository,
+) -> BTreeSet<&'a str> {
+    let org_prefix = format!("{}/", repo.organization);
+    config
+        .owners
+        .values()
+        .chain(config.adhoc_groups.values())
+        .flatten()
+        .map(|name| name.strip_prefix('@').unwrap_or(name))
+        .filter(|name| {
+            let maybe_group = name.strip_prefix(&org_prefix).unwrap_or(name);
+            let maybe_team = name.strip_prefix("rust-lang/").unwrap_or(name);
+            !config.adhoc_groups.contains_key(maybe_group) && !teams.teams.contains_key(maybe_team)
+        })
+        .collect()
+}
+
+/// Returns whether the Zulip stream with the given ID exists, or `None` if
+/// Zulip can't be queried.
+async fn zulip_stream_exists(ctx: &Context, stream: u64) -> anyhow::Result<Option<bool>> {
+    let Ok(bot_api_token) = std::env::var("ZULIP_API_TOKEN") else {
+        return Ok(None);
+    };
+    let req = ctx
+        .github
+        .raw()
+        .get(format!(
+            "https://rust-lang.zulipchat.com/api/v1/streams/{stream}"
+        ))
+        .basic_auth(BOT_EMAIL, Some(&bot_api_token));
+    let resp = ctx
+        .github
+        .send_raw(req)
+        .await
+        .with_context(|| format!("failed to query Zulip stream {stream}"))?;
+    match resp.status() {
+        StatusCode::OK => Ok(Some(true)),
+        // Zulip responds with "Invalid stream ID".
+        StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => Ok(Some(false)),
+        status => anyhow::bail!("failed to query Zulip stream {stream}: {status}"),
+    }
+}
+
+/// Prefixes the problem with the line it is on, if it is known.
+fn located(line: Option<usize>, problem: String) -> String {
+    match line {
+        Some(line) => format!("line {line}: {problem}"),
+        None => problem,
+    }
+}
+
+/// Where the keys and values are in `triagebot.toml`, based on the spans
+/// from the TOML parser.
+#[derive(Debug, Default)]
+struct Locations {
+    /// The keys and values, sorted by line.
+    entries: Vec<Location>,
+}
+
+#[derive(Debug)]
+struct Location {
+    /// The dotted path of the key, or of the key the value belongs to.
+    path: String,
+    /// The key itself, or the value as a string.
+    text: String,
+    is_key: bool,
+    line: usize,
+}
+
+impl Locations {
+    /// Finds the keys and values in `contents`. Nothing is found if it isn't
+    /// valid TOML.
+    fn new(contents: &str) -> Locations {
+        let Ok(node) = toml::from_str::<Node>(contents) else {
+            return Locations::default();
+        };
+        let mut entries = Vec::new();
+        node.flatten(contents, &mut Vec::new(), 0, &mut entries);
+        entries.sort_by_key(|entry| entry.line);
+        Locations { entries }
+    }
+
+    /// Returns the line of the key with the given dotted path, e.g.
+    /// `assign.owners./src`.
+    fn key(&self, path: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|entry| entry.is_key && entry.path == path)
+            .map(|entry| entry.line)
+    }
+
+    /// Returns the first line with a key or a value equal to `needle`. The
+    /// `@` in front of a value is ignored, since reviewers can be written
+    /// with or without it.
+    fn find(&self, needle: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|entry| {
+                entry.text == needle
+                    || (!entry.is_key && entry.text.strip_prefix('@') == Some(needle))
+            })
+            .map(|entry| entry.line)
+    }
+}
+
+/// A TOML document with the spans of the keys and values.
+enum Node {
+    Table(Vec<(toml::Spanned<String>, toml::Spanned<Node>)>),
+    Array(Vec<toml::Spanned<Node>>),
+    Value(String),
+}
+
+impl Node {
+    /// Lists the keys and values in `contents`, where this node starts at
+    /// the offset `start`.
+    fn flatten(
+        &self,
+        contents: &str,
+        path: &mut Vec<String>,
+        start: usize,
+        locations: &mut Vec<Location>,
+    ) {
+        let line = |start: usize| contents[..start].matches('\n').count() + 1;
+        match self {
+            Node::Table(entries) => {
+                for (key, value) in entries {
+                    path.push(key.get_ref().clone());
+                    locations.push(Location {
+                        path: path.join("."),
+                        text: key.get_ref().clone(),
+                        is_key: true,
+                        line: line(key.start()),
+                    });
+                    value
+                        .get_ref()
+                        .flatten(contents, path, value.start(), locations);
+                    path.pop();
+                }
+            }
+            Node::Array(values) => {
+                for value in values {
+                    value
+                        .get_ref()
+                        .flatten(contents, path, value.start(), locations);
+                }
+            }
+            Node::Value(text) => locations.push(Location {
+                path: path.join("."),
+                text: text.clone(),
+                is_key: false,
+                line: line(start),
+            }),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Node, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a TOML value")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Node, E> {
+                Ok(Node::Value(v.to_string()))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Node, E> {
+                Ok(Node::Value(v.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Node, E> {
+                Ok(Node::Value(v.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Node, E> {
+                Ok(Node::Value(v.to_string()))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Node, E> {
+                Ok(Node::Value(v.to_string()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(Node::Array(values))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Node::Table(entries))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+async fn report(
+    ctx: &Context,
+    repo: &Repository,
+    pr: &Issue,
+    head_sha: &str,
+    problems: Vec<String>,
+) -> anyhow::Result<()> {
+    // Pushes in quick succession are handled concurrently, so only report for
+    // one at a time per PR, and only for the latest commit.
+    let mut lock_client = ctx.db.get().await;
+    let _lock = issue_data::lock(&mut lock_client, pr, VALIDATE_CONFIG_KEY).await?;
+    let current = repo.get_pr(&ctx.github, pr.number).await?;
+    if !current
+        .head
+        .as_ref()
+        .is_some_and(|head| head.sha == head_sha)
+    {
+        return Ok(());
+    }
+
+    // The data itself is only locked to save it, not during the request to
+    // GitHub.
+    let state: ValidateConfigState =
+        issue_data::peek(&*ctx.db.get().await, pr, VALIDATE_CONFIG_KEY).await?;
+    if state.problems == problems {
+        return Ok(());
+    }
+    let message = if problems.is_empty() {
+        if state.comment_id.is_none() {
+            return Ok(());
+        }
+        format!("The `{CONFIG_FILE_NAME}` problems have been fixed as of commit {head_sha}.")
+    } else {
+        problems_message(head_sha, &problems)
+    };
+    let comment_id = pr
+        .upsert_comment(&ctx.github, state.comment_id, &message)
+        .await
+        .context("failed to post validate_config comment")?;
+
+    let mut client = ctx.db.get().await;
+    let mut state: IssueData<'_, ValidateConfigState> =
+        IssueData::load(&mut client, pr, VALIDATE_CONFIG_KEY).await?;
+    state.data = ValidateConfigState {
+        comment_id: Some(comment_id),
+        problems,
+    };
+    state.save().await?;
+    Ok(())
+}
+
+fn problems_message(head_sha: &str, problems: &[String]) -> String {
+    let mut message =
+        format!("The `{CONFIG_FILE_NAME}` in this PR has problems as of commit {head_sha}:\n\n");
+    for problem in problems {
+        writeln!(message, "- {problem}").unwrap();
+    }
+    message.push_str("\nThis comment will be updated when the PR is.");
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[relabel]
+allow-unauthenticated = ["C-*"]
+
+[prioritize]
+label = "I-prioritize"
+
+[autolabel."T-compiler"]
+trigger_labels = ["A-diagnostics"]
+
+[assign.adhoc_groups]
+compiler-reviewers = ["@octocat", "rust-lang/compiler"]
+
+[assign.owners]
+"/src/doc" = ["compiler-reviewers", "rust-lang/missing"]
+"/compiler/**/[abc" = ["compiler"]
+"#;
+
+    #[test]
+    fn parse_error_has_line() {
+        let err = parse(None, "[prioritize]\nlabel =\n").unwrap_err();
+        assert!(err[0].contains("line 2"), "{err:?}");
+        let err = parse(
+            None,
+            "[prioritize]\nlabel = \"I-prioritize\"\nlable = \"\"\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ["line 3: unknown key `prioritize.lable` (did you mean `label`?)"]
+        );
+    }
+
+    #[test]
+    fn locations() {
+        let locations = Locations::new(CONFIG);
+        // Only whole keys and values match, not the `T-compiler` on line 8.
+        assert_eq!(locations.find("compiler"), Some(16));
+        assert_eq!(locations.find("octocat"), Some(12));
+        assert_eq!(locations.find("rust-lang/missing"), Some(15));
+        assert_eq!(locations.key("assign.owners./src/doc"), Some(15));
+        assert_eq!(locations.key("prioritize.label"), Some(6));
+        assert_eq!(locations.find("missing"), None);
+        assert!(Locations::new("[prioritize\n").entries.is_empty());
+    }
+
+    #[test]
+    fn labels() {
+        let config = parse(None, CONFIG).unwrap();
+        assert_eq!(
+            referenced_labels(&config).into_iter().collect::<Vec<_>>(),
+            ["A-diagnostics", "I-prioritize", "T-compiler"]
+        );
+    }
+
+    #[test]
+    fn owners() {
+        let config = parse(None, CONFIG).unwrap();
+        let assign = config.assign.as_ref().unwrap();
+        let problems = owner_pattern_problems(assign, &Locations::new(CONFIG));
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("line 16: the owners pattern `/compiler/**/[abc`"),
+            "{}",
+            problems[0]
+        );
+
+        let teams: Teams = serde_json::from_value(serde_json::json!({
+            "compiler": {
+                "name": "compiler",
+                "kind": "team",
+                "members": [],
+                "alumni": [],
+                "discord": [],
+            },
+        }))
+        .unwrap();
+        let repo = IssueRepository {
+            organization: "rust-lang".to_string(),
+            repository: "rust".to_string(),
+        };
+        assert_eq!(
+            unresolved_reviewers(assign, &teams, &repo)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["octocat", "rust-lang/missing"]
+        );
+    }
+
+    #[test]
+    fn aliases() {
+        let contents = "[shortcut.needs-triage]\n\
+                        add = [\"needs-triage\"]\n\
+                        \n\
+                        [aliases]\n\
+                        ptal = \"ready\"\n\
+                        triage = \"needs-triage\"\n\
+                        nom = \"nominate\"\n\
+                        oops = \"does not exist\"\n\
+                        close = \"ready\"\n\
+                        \n\
+                        [shortcut.help]\n\
+                        add = [\"help\"]\n";
+        let config = parse(None, contents).unwrap();
+        assert_eq!(
+            referenced_labels(&config).into_iter().collect::<Vec<_>>(),
+            ["needs-triage"]
+        );
+        assert_eq!(
+            alias_problems(&config, &Locations::new(contents)),
+            [
+                "line 11: the shortcut `help` is ignored, since it is a built-in command",
+                "line 9: the alias `close` is ignored, since it is a built-in command",
+                "line 7: the alias `nom` expands to an invalid command `nominate`",
+                "line 8: the alias `oops` doesn't expand to a command",
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Liveness and readiness checks, served from `/health/live` and
+//! `/health/ready`.
+
+use crate::github::User;
+use crate::handlers::Context;
+use crate::jobs::{JOB_PROCESSING_CADENCE_IN_SECS, JOB_SCHEDULING_CADENCE_IN_SECS};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Records the last time a background task made progress.
+pub struct Heartbeat(AtomicI64);
+
+impl Heartbeat {
+    const fn new() -> Self {
+        Heartbeat(AtomicI64::new(0))
+    }
+
+    pub fn beat(&self) {
+        self.0.store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    pub fn last(&self) -> Option<DateTime<Utc>> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            secs => Utc.timestamp_opt(secs, 0).single(),
+        }
+    }
+}
+
+/// Updated every time `spawn_job_scheduler` schedules jobs.
+pub static JOB_SCHEDULER: Heartbeat = Heartbeat::new();
+/// Updated every time `spawn_job_runner` runs the jobs which are due.
+pub static JOB_RUNNER: Heartbeat = Heartbeat::new();
+/// Updated every time a webhook worker checks the webhook inbox.
+pub static WEBHOOK_WORKERS: Heartbeat = Heartbeat::new();
+/// Updated every time the GitHub API accepts our token.
+pub static GITHUB_TOKEN: Heartbeat = Heartbeat::new();
+
+/// How long the GitHub token is trusted before the readiness check confirms
+/// it again.
+const GITHUB_TOKEN_MAX_AGE_SECS: i64 = 10 * 60;
+
+/// How long readiness checks wait for the database.
+const DATABASE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Serialize, Debug)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Check {
+    pub ok: bool,
+    /// When this was last confirmed to be working, for checks of background
+    /// tasks and the GitHub token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_confirmed: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Check {
+    fn result(result: anyhow::Result<()>) -> Check {
+        Check {
+            ok: result.is_ok(),
+            last_confirmed: None,
+            error: result.err().map(|e| format!("{:?}", e)),
+        }
+    }
+
+    /// Checks that the heartbeat was updated in the last `max_age_secs`
+    /// seconds.
+    fn heartbeat(heartbeat: &Heartbeat, max_age_secs: u64) -> Check {
+        let last = heartbeat.last();
+        let ok = last.map_or(false, |last| {
+            Utc::now() - last <= Duration::seconds(max_age_secs as i64)
+        });
+        Check {
+            ok,
+            last_confirmed: last,
+            error: (!ok).then(|| format!("no progress in the last {max_age_secs} seconds")),
+        }
+    }
+}
+
+/// Checks that the database, the background tasks and the GitHub token all
+/// work.
+///
+/// `jobs_enabled` is false if the scheduled jobs have been disabled, in which
+/// case their tasks aren't checked.
+pub async fn readiness(ctx: &Context, jobs_enabled: bool) -> Readiness {
+    let mut checks = BTreeMap::new();
+
+    match tokio::time::timeout(DATABASE_TIMEOUT, ctx.db.try_get()).await {
+        Ok(Ok(db)) => {
+            checks.insert("database", Check::result(Ok(())));
+            let migrations =
+                tokio::time::timeout(DATABASE_TIMEOUT, crate::db::migration_status(&db))
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out")))
+                    .and_then(|(applied, expected)| {
+                        if applied == expected {
+                            Ok(())
+                        } else {
+                            Err(anyhow::anyhow!(
+                                "{applied} of {expected} migrations have been applied"
+                            ))
+                        }
+                    });
+            checks.insert("migrations", Check::result(migrations));
+        }
+        Ok(Err(e)) => {
+            checks.insert("database", Check::result(Err(e)));
+        }
+        Err(_) => {
+            checks.insert(
+                "database",
+                Check::result(Err(anyhow::anyhow!("timed out connecting"))),
+            );
+        }
+    }
+
+    if jobs_enabled {
+        // Allow a missed tick before considering the task dead.
+        checks.insert(
+            "job_scheduler",
+            Check::heartbeat(&JOB_SCHEDULER, 2 * JOB_SCHEDULING_CADENCE_IN_SECS + 60),
+        );
+        checks.insert(
+            "job_runner",
+            Check::heartbeat(&JOB_RUNNER, 2 * JOB_PROCESSING_CADENCE_IN_SECS + 60),
+        );
+    }
+    // The workers only check in once the inbox is drained, so give slow
+    // handlers some leeway.
+    checks.insert(
+        "webhook_workers",
+        Check::heartbeat(&WEBHOOK_WORKERS, 10 * 60),
+    );
+
+    let token_is_fresh = GITHUB_TOKEN.last().map_or(false, |last| {
+        Utc::now() - last <= Duration::seconds(GITHUB_TOKEN_MAX_AGE_SECS)
+    });
+    let token = if token_is_fresh {
+        Ok(())
+    } else {
+        User::current(&ctx.github)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.context("failed to confirm the GitHub token"))
+    };
+    checks.insert(
+        "github_token",
+        Check {
+            last_confirmed: GITHUB_TOKEN.last(),
+            ..Check::result(token)
+        },
+    );
+
+    Readiness {
+        ready: checks.values().all(|check| check.ok),
+        checks,
+    }
+}
@@ -8,6 +8,7 @@ use crate::github::PullRequestDetails;
 use anyhow::Context;
 use handlers::HandlerError;
 use interactions::ErrorComment;
+use std::collections::HashSet;
 use std::fmt;
 use tracing as log;
 
@@ -18,9 +19,11 @@ pub mod config;
 pub mod db;
 pub mod github;
 pub mod handlers;
+pub mod health;
 pub mod http_client;
 pub mod interactions;
 pub mod jobs;
+pub mod metrics;
 pub mod notification_listing;
 pub mod payload;
 pub mod rfcbot;
@@ -74,6 +77,45 @@ pub enum EventName {
     ///
     /// <https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#create>
     Create,
+    /// Check run activity.
+    ///
+    /// This gets translated to [`github::Event::CheckRun`] when sent to a handler.
+    ///
+    /// <https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#check_run>
+    CheckRun,
+    /// Check suite activity.
+    ///
+    /// This gets translated to [`github::Event::CheckSuite`] when sent to a handler.
+    ///
+    /// <https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#check_suite>
+    CheckSuite,
+    /// A GitHub Actions workflow run was requested, started, or completed.
+    ///
+    /// This gets translated to [`github::Event::WorkflowRun`] when sent to a handler.
+    ///
+    /// <https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#workflow_run>
+    WorkflowRun,
+    /// A discussion was created, edited, labeled, etc.
+    ///
+    /// Created and edited discussions get translated to
+    /// [`github::Event::Issue`] when sent to a handler, so that commands in
+    /// the top post are handled.
+    ///
+    /// <https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#discussion>
+    Discussion,
+    /// A comment on a discussion.
+    ///
+    /// This gets translated to [`github::Event::IssueComment`] when sent to a handler.
+    ///
+    /// <https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#discussion_comment>
+    DiscussionComment,
+    /// The GitHub App was installed, uninstalled, or its installation changed.
+    ///
+    /// This isn't sent to handlers, it only keeps the list of installations
+    /// of the app up to date.
+    ///
+    /// <https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#installation>
+    Installation,
     /// All other unhandled webhooks.
     Other,
 }
@@ -89,6 +131,12 @@ impl std::str::FromStr for EventName {
             "issues" => EventName::Issue,
             "push" => EventName::Push,
             "create" => EventName::Create,
+            "check_run" => EventName::CheckRun,
+            "check_suite" => EventName::CheckSuite,
+            "workflow_run" => EventName::WorkflowRun,
+            "discussion" => EventName::Discussion,
+            "discussion_comment" => EventName::DiscussionComment,
+            "installation" => EventName::Installation,
             _ => EventName::Other,
         })
     }
@@ -107,6 +155,12 @@ impl fmt::Display for EventName {
                 EventName::PullRequest => "pull_request",
                 EventName::Push => "push",
                 EventName::Create => "create",
+                EventName::CheckRun => "check_run",
+                EventName::CheckSuite => "check_suite",
+                EventName::WorkflowRun => "workflow_run",
+                EventName::Discussion => "discussion",
+                EventName::DiscussionComment => "discussion_comment",
+                EventName::Installation => "installation",
                 EventName::Other => "other",
             }
         )
@@ -114,11 +168,23 @@ impl fmt::Display for EventName {
 }
 
 #[derive(Debug)]
-pub struct WebhookError(anyhow::Error);
+pub enum WebhookError {
+    /// The payload could not be deserialized, so retrying won't help.
+    Payload(anyhow::Error),
+    /// Some handlers failed, and may work if they are retried.
+    Handler(anyhow::Error),
+}
+
+impl WebhookError {
+    /// Whether handling the webhook again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, WebhookError::Handler(_))
+    }
+}
 
 impl From<anyhow::Error> for WebhookError {
     fn from(e: anyhow::Error) -> WebhookError {
-        WebhookError(e)
+        WebhookError::Handler(e)
     }
 }
 
@@ -134,19 +200,45 @@ pub fn deserialize_payload<T: serde::de::DeserializeOwned>(v: &str) -> anyhow::R
     }
 }
 
+/// Handles a webhook.
+///
+/// The handlers named in `finished` already ran for a previous attempt at
+/// this webhook and are skipped. The handlers which don't fail are added to
+/// it, so that a retry only runs the ones which failed.
 pub async fn webhook(
     event: EventName,
     payload: String,
     ctx: &handlers::Context,
+    finished: &mut HashSet<String>,
+) -> Result<bool, WebhookError> {
+    let event_name = event.to_string();
+    let result = handle_webhook(event, payload, ctx, finished).await;
+    let outcome = match &result {
+        Ok(true) => "processed",
+        Ok(false) => "ignored",
+        Err(_) => "failed",
+    };
+    metrics::WEBHOOKS
+        .with_label_values(&[&event_name, outcome])
+        .inc();
+    result
+}
+
+async fn handle_webhook(
+    event: EventName,
+    payload: String,
+    ctx: &handlers::Context,
+    finished: &mut HashSet<String>,
 ) -> Result<bool, WebhookError> {
     let event = match event {
         EventName::PullRequestReview => {
             let mut payload = deserialize_payload::<github::PullRequestReviewEvent>(&payload)
                 .context("PullRequestReview failed to deserialize")
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::Payload)?;
 
             log::info!("handling pull request review comment {:?}", payload);
             payload.pull_request.pull_request = Some(PullRequestDetails {});
+            payload.review.kind = github::CommentKind::Review;
 
             // Treat pull request review comments exactly like pull request
             // review comments.
@@ -169,9 +261,10 @@ pub async fn webhook(
         EventName::PullRequestReviewComment => {
             let mut payload = deserialize_payload::<github::PullRequestReviewComment>(&payload)
                 .context("PullRequestReview(Comment) failed to deserialize")
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::Payload)?;
 
             payload.issue.pull_request = Some(PullRequestDetails {});
+            payload.comment.kind = github::CommentKind::ReviewComment;
 
             log::info!("handling pull request review comment {:?}", payload);
 
@@ -188,7 +281,7 @@ pub async fn webhook(
         EventName::IssueComment => {
             let payload = deserialize_payload::<github::IssueCommentEvent>(&payload)
                 .context("IssueCommentEvent failed to deserialize")
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::Payload)?;
 
             log::info!("handling issue comment {:?}", payload);
 
@@ -197,7 +290,7 @@ pub async fn webhook(
         EventName::Issue | EventName::PullRequest => {
             let mut payload = deserialize_payload::<github::IssuesEvent>(&payload)
                 .context(format!("{:?} failed to deserialize", event))
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::Payload)?;
 
             if matches!(event, EventName::PullRequest) {
                 payload.issue.pull_request = Some(PullRequestDetails {});
@@ -210,7 +303,7 @@ pub async fn webhook(
         EventName::Push => {
             let payload = deserialize_payload::<github::PushEvent>(&payload)
                 .with_context(|| format!("{:?} failed to deserialize", event))
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::Payload)?;
 
             log::info!("handling push event {:?}", payload);
 
@@ -219,18 +312,95 @@ pub async fn webhook(
         EventName::Create => {
             let payload = deserialize_payload::<github::CreateEvent>(&payload)
                 .with_context(|| format!("{:?} failed to deserialize", event))
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::Payload)?;
 
             log::info!("handling create event {:?}", payload);
 
             github::Event::Create(payload)
         }
+        EventName::CheckRun => {
+            let payload = deserialize_payload::<github::CheckRunEvent>(&payload)
+                .with_context(|| format!("{:?} failed to deserialize", event))
+                .map_err(WebhookError::Payload)?;
+
+            log::info!("handling check run event {:?}", payload);
+
+            github::Event::CheckRun(payload)
+        }
+        EventName::CheckSuite => {
+            let payload = deserialize_payload::<github::CheckSuiteEvent>(&payload)
+                .with_context(|| format!("{:?} failed to deserialize", event))
+                .map_err(WebhookError::Payload)?;
+
+            log::info!("handling check suite event {:?}", payload);
+
+            github::Event::CheckSuite(payload)
+        }
+        EventName::WorkflowRun => {
+            let payload = deserialize_payload::<github::WorkflowRunEvent>(&payload)
+                .with_context(|| format!("{:?} failed to deserialize", event))
+                .map_err(WebhookError::Payload)?;
+
+            log::info!("handling workflow run event {:?}", payload);
+
+            github::Event::WorkflowRun(payload)
+        }
+        EventName::Discussion => {
+            let payload = deserialize_payload::<github::DiscussionEvent>(&payload)
+                .with_context(|| format!("{:?} failed to deserialize", event))
+                .map_err(WebhookError::Payload)?;
+
+            log::info!("handling discussion event {:?}", payload);
+
+            // Only creating or editing the top post can add commands, so
+            // other actions (including unknown ones) are ignored.
+            let action = match payload.action {
+                github::discussion::DiscussionAction::Created => github::IssuesAction::Opened,
+                github::discussion::DiscussionAction::Edited => github::IssuesAction::Edited,
+                _ => return Ok(false),
+            };
+            github::Event::Issue(github::IssuesEvent {
+                action,
+                issue: payload.discussion.into_issue(&payload.repository),
+                changes: payload.changes,
+                repository: payload.repository,
+                label: None,
+            })
+        }
+        EventName::DiscussionComment => {
+            let payload = deserialize_payload::<github::DiscussionCommentEvent>(&payload)
+                .with_context(|| format!("{:?} failed to deserialize", event))
+                .map_err(WebhookError::Payload)?;
+
+            log::info!("handling discussion comment {:?}", payload);
+
+            // Treat discussion comments exactly like issue comments.
+            github::Event::IssueComment(github::IssueCommentEvent {
+                action: payload.action,
+                changes: payload.changes,
+                issue: payload.discussion.into_issue(&payload.repository),
+                comment: payload.comment,
+                repository: payload.repository,
+            })
+        }
+        EventName::Installation => {
+            let payload = deserialize_payload::<github::InstallationEvent>(&payload)
+                .with_context(|| format!("{:?} failed to deserialize", event))
+                .map_err(WebhookError::Payload)?;
+
+            log::info!("handling installation event {:?}", payload);
+
+            if let Some(app) = ctx.github.app() {
+                app.handle_installation_event(&payload);
+            }
+            return Ok(true);
+        }
         // Other events need not be handled
         EventName::Other => {
             return Ok(false);
         }
     };
-    let errors = handlers::handle(&ctx, &event).await;
+    let errors = handlers::handle(&ctx, &event, finished).await;
     let mut other_error = false;
     let mut message = String::new();
     for err in errors {
@@ -249,12 +419,16 @@ pub async fn webhook(
     }
     if !message.is_empty() {
         if let Some(issue) = event.issue() {
+            // The handlers which produced the message already count as
+            // finished, so a retry wouldn't post it either.
             let cmnt = ErrorComment::new(issue, message);
-            cmnt.post(&ctx.github).await?;
+            if let Err(e) = cmnt.post(&ctx.github).await {
+                log::error!("failed to post error comment: {:?}", e);
+            }
         }
     }
     if other_error {
-        Err(WebhookError(anyhow::anyhow!(
+        Err(WebhookError::Handler(anyhow::anyhow!(
             "handling failed, error logged",
         )))
     } else {
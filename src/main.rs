@@ -12,7 +12,13 @@ use tower::{Service, ServiceExt};
 use tracing as log;
 use tracing::Instrument;
 use triagebot::jobs::{jobs, JOB_PROCESSING_CADENCE_IN_SECS, JOB_SCHEDULING_CADENCE_IN_SECS};
-use triagebot::{db, github, handlers::Context, notification_listing, payload, EventName};
+use triagebot::{db, github, handlers::Context, health, notification_listing, payload, EventName};
+
+// How many webhooks are processed concurrently.
+const WEBHOOK_WORKER_COUNT: usize = 4;
+
+// How often the webhook workers check for newly queued webhooks.
+const WEBHOOK_PROCESSING_CADENCE_IN_MILLIS: u64 = 500;
 
 async fn handle_agenda_request(req: String) -> anyhow::Result<String> {
     if req == "/agenda/lang/triage" {
@@ -82,6 +88,95 @@ async fn serve_req(
             .body(Body::from("Triagebot is awaiting triage."))
             .unwrap());
     }
+    if req.uri.path() == "/health/live" {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"live":true}"#))
+            .unwrap());
+    }
+    if req.uri.path() == "/health/ready" {
+        let readiness = health::readiness(&ctx, !is_scheduled_jobs_disabled()).await;
+        return Ok(Response::builder()
+            .status(if readiness.ready {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            })
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_string(&readiness).unwrap()))
+            .unwrap());
+    }
+    if req.uri.path() == "/metrics" {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(triagebot::metrics::render()))
+            .unwrap());
+    }
+    if req.uri.path() == "/webhook-deliveries" {
+        // The error messages may include details of private repositories.
+        if let Err(status) = authorize_admin(&req.headers) {
+            return Ok(Response::builder()
+                .status(status)
+                .body(Body::empty())
+                .unwrap());
+        }
+        let res = db::webhook_deliveries::get_dead_deliveries(&*ctx.db.get().await).await;
+        let res = match res {
+            Ok(r) => r,
+            Err(e) => {
+                return Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Body::from(format!("{:?}", e)))
+                    .unwrap());
+            }
+        };
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_string(&res).unwrap()))
+            .unwrap());
+    }
+    if req.uri.path() == "/triagebot.schema.json" {
+        let schema = triagebot::config::json_schema();
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/schema+json")
+            .body(Body::from(serde_json::to_string_pretty(&schema).unwrap()))
+            .unwrap());
+    }
+    if req.uri.path() == "/admin/config-cache" {
+        if let Err(status) = authorize_admin(&req.headers) {
+            return Ok(Response::builder()
+                .status(status)
+                .body(Body::empty())
+                .unwrap());
+        }
+        if req.method != hyper::Method::POST {
+            return Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "POST")
+                .body(Body::empty())
+                .unwrap());
+        }
+        let repo = req.uri.query().and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(k, _)| k == "repo")
+                .map(|(_, repo)| repo.into_owned())
+        });
+        let evicted = match &repo {
+            Some(repo) => triagebot::config::evict(repo),
+            None => triagebot::config::evict_all(),
+        };
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "repo": repo, "evicted": evicted }).to_string(),
+            ))
+            .unwrap());
+    }
     if req.uri.path() == "/bors-commit-list" {
         let res = db::rustc_commits::get_commits_with_artifacts(&*ctx.db.get().await).await;
         let res = match res {
@@ -179,22 +274,28 @@ async fn serve_req(
             .unwrap());
     };
     log::debug!("event={}", event);
-    let signature = if let Some(sig) = req.headers.get("X-Hub-Signature") {
-        match sig.to_str().ok() {
-            Some(v) => v,
-            None => {
-                return Ok(Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from("X-Hub-Signature header must be UTF-8 encoded"))
-                    .unwrap());
-            }
-        }
+    let (algorithm, signature_header) = if req.headers.contains_key("X-Hub-Signature-256") {
+        (payload::SignatureAlgorithm::Sha256, "X-Hub-Signature-256")
+    } else if payload::is_sha1_signature_allowed() && req.headers.contains_key("X-Hub-Signature") {
+        (payload::SignatureAlgorithm::Sha1, "X-Hub-Signature")
     } else {
+        log::warn!("rejecting webhook: X-Hub-Signature-256 header not set");
         return Ok(Response::builder()
             .status(StatusCode::BAD_REQUEST)
-            .body(Body::from("X-Hub-Signature header must be set"))
+            .body(Body::from("X-Hub-Signature-256 header must be set"))
             .unwrap());
     };
+    let signature = match req.headers[signature_header].to_str().ok() {
+        Some(v) => v,
+        None => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!(
+                    "{signature_header} header must be UTF-8 encoded"
+                )))
+                .unwrap());
+        }
+    };
     log::debug!("signature={}", signature);
 
     let mut c = body_stream;
@@ -204,7 +305,10 @@ async fn serve_req(
         payload.extend_from_slice(&chunk);
     }
 
-    if let Err(_) = payload::assert_signed(signature, &payload) {
+    if let Err(e) =
+        payload::assert_signed(algorithm, signature, &payload, &payload::webhook_secrets())
+    {
+        log::warn!("rejecting webhook with {signature_header}: {e}");
         return Ok(Response::builder()
             .status(StatusCode::FORBIDDEN)
             .body(Body::from("Wrong signature"))
@@ -220,9 +324,41 @@ async fn serve_req(
         }
     };
 
-    match triagebot::webhook(event, payload, &ctx).await {
-        Ok(true) => Ok(Response::new(Body::from("processed request"))),
-        Ok(false) => Ok(Response::new(Body::from("ignored request"))),
+    if matches!(event, EventName::Other) {
+        return Ok(Response::new(Body::from("ignored request")));
+    }
+
+    // The handlers run in the background (see `spawn_webhook_workers`), so
+    // that slow handlers don't cause GitHub to time out and redeliver the
+    // webhook. Redeliveries of a webhook which is already queued or was
+    // handled are ignored.
+    let delivery_id = match req
+        .headers
+        .get("X-GitHub-Delivery")
+        .and_then(|id| id.to_str().ok())
+    {
+        Some(id) => id.to_string(),
+        None => uuid::Uuid::new_v4().to_string(),
+    };
+    match db::webhook_deliveries::enqueue_delivery(
+        &*ctx.db.get().await,
+        &delivery_id,
+        &event.to_string(),
+        &payload,
+    )
+    .await
+    {
+        Ok(true) => Ok(Response::builder()
+            .status(StatusCode::ACCEPTED)
+            .body(Body::from("queued request"))
+            .unwrap()),
+        Ok(false) => {
+            log::info!("ignoring redelivery of {}", delivery_id);
+            triagebot::metrics::WEBHOOKS
+                .with_label_values(&[&event.to_string(), "duplicate"])
+                .inc();
+            Ok(Response::new(Body::from("ignored request")))
+        }
         Err(err) => {
             log::error!("request failed: {:?}", err);
             Ok(Response::builder()
@@ -233,6 +369,26 @@ async fn serve_req(
     }
 }
 
+/// Checks the `Authorization: Bearer <token>` header of a request to an
+/// admin route against `TRIAGEBOT_ADMIN_TOKEN`. The admin routes don't exist
+/// if it isn't set.
+fn authorize_admin(headers: &header::HeaderMap) -> Result<(), StatusCode> {
+    let Ok(expected) = env::var("TRIAGEBOT_ADMIN_TOKEN") else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    // `memcmp::eq` panics if the lengths differ.
+    if token.len() == expected.len() && openssl::memcmp::eq(token.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
 async fn run_server(addr: SocketAddr) -> anyhow::Result<()> {
     let pool = db::ClientPool::new();
     db::run_migrations(&*pool.get().await)
@@ -256,6 +412,7 @@ async fn run_server(addr: SocketAddr) -> anyhow::Result<()> {
         spawn_job_scheduler();
         spawn_job_runner(ctx.clone());
     }
+    spawn_webhook_workers(ctx.clone());
 
     let agenda = tower::ServiceBuilder::new()
         .buffer(10)
@@ -321,6 +478,7 @@ fn spawn_job_scheduler() {
                         .await
                         .context("database schedule jobs")
                         .unwrap();
+                    health::JOB_SCHEDULER.beat();
                 }
             });
 
@@ -357,6 +515,7 @@ fn spawn_job_runner(ctx: Arc<Context>) {
                         .await
                         .context("run database scheduled jobs")
                         .unwrap();
+                    health::JOB_RUNNER.beat();
                 }
             });
 
@@ -372,6 +531,49 @@ fn spawn_job_runner(ctx: Arc<Context>) {
     });
 }
 
+/// Spawns background tokio tasks which run continuously to process the
+/// webhooks queued in the `webhook_deliveries` table.
+///
+/// Each worker wakes up every `WEBHOOK_PROCESSING_CADENCE_IN_MILLIS`
+/// milliseconds and processes webhooks until none are ready. Failed webhooks
+/// are retried with exponential backoff until they run out of attempts.
+fn spawn_webhook_workers(ctx: Arc<Context>) {
+    let pool = Arc::new(db::ClientPool::new());
+    for _ in 0..WEBHOOK_WORKER_COUNT {
+        let ctx = ctx.clone();
+        let pool = pool.clone();
+        task::spawn(async move {
+            loop {
+                let ctx = ctx.clone();
+                let pool = pool.clone();
+                let res = task::spawn(async move {
+                    let mut interval = time::interval(time::Duration::from_millis(
+                        WEBHOOK_PROCESSING_CADENCE_IN_MILLIS,
+                    ));
+
+                    loop {
+                        interval.tick().await;
+                        db::process_webhook_deliveries(&ctx, &*pool.get().await)
+                            .await
+                            .context("process webhook deliveries")
+                            .unwrap();
+                        health::WEBHOOK_WORKERS.beat();
+                    }
+                });
+
+                match res.await {
+                    Err(err) if err.is_panic() => {
+                        /* handle panic in above task, re-launching */
+                        tracing::error!("process_webhook_deliveries task died (error={err})");
+                        tokio::time::sleep(std::time::Duration::new(5, 0)).await;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        });
+    }
+}
+
 /// Determines whether or not background scheduled jobs should be disabled for
 /// the purpose of testing.
 ///
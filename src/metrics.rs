@@ -0,0 +1,75 @@
+//! Prometheus metrics, served from the `/metrics` endpoint.
+
+use crate::handlers::HandlerError;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+use std::time::Instant;
+
+lazy_static! {
+    /// Webhooks which went through the handlers, by event name and outcome
+    /// (`processed`, `ignored` or `failed`), plus redeliveries which were
+    /// skipped (`duplicate`).
+    pub static ref WEBHOOKS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_webhooks_total",
+        "Number of webhooks received, by event and outcome",
+        &["event", "outcome"]
+    )
+    .unwrap();
+    /// Time spent in each handler. `dispatch` is either `issue` or `command`,
+    /// and `outcome` is `ok`, `message` (an error reported back to the user)
+    /// or `error` (an internal error).
+    pub static ref HANDLERS: HistogramVec = register_histogram_vec!(
+        "triagebot_handler_duration_seconds",
+        "Time spent running a handler, by handler and outcome",
+        &["dispatch", "handler", "outcome"]
+    )
+    .unwrap();
+    /// Requests sent to the GitHub API, by HTTP method and response status.
+    pub static ref GITHUB_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_github_requests_total",
+        "Number of GitHub API requests, by method and response status",
+        &["method", "status"]
+    )
+    .unwrap();
+    /// Scheduled job runs, by job name and outcome (`succeeded` or `failed`).
+    pub static ref JOB_RUNS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_job_runs_total",
+        "Number of scheduled job runs, by job and outcome",
+        &["job", "outcome"]
+    )
+    .unwrap();
+    /// Scheduled jobs which are due but haven't completed successfully yet.
+    pub static ref JOB_QUEUE_DEPTH: IntGauge = register_int_gauge!(
+        "triagebot_job_queue_depth",
+        "Number of scheduled jobs which are due but not completed"
+    )
+    .unwrap();
+}
+
+/// Records how long a handler took, and whether it pushed any errors.
+///
+/// `errors` are the errors the handler added to the list of errors for the
+/// event.
+pub fn observe_handler(dispatch: &str, handler: &str, start: Instant, errors: &[HandlerError]) {
+    let outcome = if errors.iter().any(|e| matches!(e, HandlerError::Other(_))) {
+        "error"
+    } else if errors.is_empty() {
+        "ok"
+    } else {
+        "message"
+    };
+    HANDLERS
+        .with_label_values(&[dispatch, handler, outcome])
+        .observe(start.elapsed().as_secs_f64());
+}
+
+/// Renders all metrics in the Prometheus text format.
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
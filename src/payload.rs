@@ -1,39 +1,179 @@
 use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
 use std::fmt;
 
-#[derive(Debug)]
-pub struct SignedPayloadError;
+/// The reason a webhook payload was rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignedPayloadError {
+    /// The signature was not prefixed with the expected `<algorithm>=`.
+    WrongAlgorithm,
+    /// The signature was not valid hex.
+    MalformedHex,
+    /// None of the configured secrets produced the given signature.
+    NoMatchingSecret,
+}
 
 impl fmt::Display for SignedPayloadError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "failed to validate payload")
+        match self {
+            SignedPayloadError::WrongAlgorithm => {
+                write!(f, "failed to validate payload: wrong signature algorithm")
+            }
+            SignedPayloadError::MalformedHex => {
+                write!(f, "failed to validate payload: malformed hex signature")
+            }
+            SignedPayloadError::NoMatchingSecret => {
+                write!(f, "failed to validate payload: no matching secret")
+            }
+        }
     }
 }
 
 impl std::error::Error for SignedPayloadError {}
 
-pub fn assert_signed(signature: &str, payload: &[u8]) -> Result<(), SignedPayloadError> {
-    let signature = signature.get("sha1=".len()..).ok_or(SignedPayloadError)?;
+/// The HMAC algorithm used to sign a webhook payload.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    /// HMAC-SHA256, sent by GitHub in the `X-Hub-Signature-256` header.
+    Sha256,
+    /// HMAC-SHA1, sent by GitHub in the legacy `X-Hub-Signature` header.
+    Sha1,
+}
+
+impl SignatureAlgorithm {
+    fn prefix(self) -> &'static str {
+        match self {
+            SignatureAlgorithm::Sha256 => "sha256=",
+            SignatureAlgorithm::Sha1 => "sha1=",
+        }
+    }
+
+    fn digest(self) -> MessageDigest {
+        match self {
+            SignatureAlgorithm::Sha256 => MessageDigest::sha256(),
+            SignatureAlgorithm::Sha1 => MessageDigest::sha1(),
+        }
+    }
+}
+
+/// Returns the webhook secrets which are currently accepted.
+///
+/// `GITHUB_WEBHOOK_SECRET` may contain several comma-separated secrets so
+/// that the secret can be rotated without downtime: add the new secret,
+/// update the webhook on GitHub, and then remove the old secret.
+pub fn webhook_secrets() -> Vec<String> {
+    std::env::var("GITHUB_WEBHOOK_SECRET")
+        .expect("Missing GITHUB_WEBHOOK_SECRET")
+        .split(',')
+        .map(|secret| secret.trim())
+        .filter(|secret| !secret.is_empty())
+        .map(|secret| secret.to_string())
+        .collect()
+}
+
+/// Whether or not the legacy `X-Hub-Signature` (HMAC-SHA1) header is
+/// accepted when `X-Hub-Signature-256` is missing, which is the case when
+/// `GITHUB_WEBHOOK_ALLOW_SHA1` is `1` or `true`.
+pub fn is_sha1_signature_allowed() -> bool {
+    std::env::var("GITHUB_WEBHOOK_ALLOW_SHA1").is_ok_and(|value| is_enabled(&value))
+}
+
+/// Whether the value of a boolean environment variable turns it on. Anything
+/// other than `1` or `true` (such as `0` or `false`) leaves it off.
+fn is_enabled(value: &str) -> bool {
+    let value = value.trim();
+    value == "1" || value.eq_ignore_ascii_case("true")
+}
+
+pub fn assert_signed(
+    algorithm: SignatureAlgorithm,
+    signature: &str,
+    payload: &[u8],
+    secrets: &[String],
+) -> Result<(), SignedPayloadError> {
+    let signature = signature
+        .strip_prefix(algorithm.prefix())
+        .ok_or(SignedPayloadError::WrongAlgorithm)?;
     let signature = match hex::decode(&signature) {
         Ok(e) => e,
         Err(e) => {
             tracing::trace!("hex decode failed for {:?}: {:?}", signature, e);
-            return Err(SignedPayloadError);
+            return Err(SignedPayloadError::MalformedHex);
         }
     };
 
-    let key = PKey::hmac(
-        std::env::var("GITHUB_WEBHOOK_SECRET")
-            .expect("Missing GITHUB_WEBHOOK_SECRET")
-            .as_bytes(),
-    )
-    .unwrap();
-    let mut signer = Signer::new(MessageDigest::sha1(), &key).unwrap();
-    signer.update(&payload).unwrap();
-    let hmac = signer.sign_to_vec().unwrap();
+    for secret in secrets {
+        let key = PKey::hmac(secret.as_bytes()).unwrap();
+        let mut signer = Signer::new(algorithm.digest(), &key).unwrap();
+        signer.update(&payload).unwrap();
+        let hmac = signer.sign_to_vec().unwrap();
+
+        // `memcmp::eq` panics if the lengths differ.
+        if hmac.len() == signature.len() && memcmp::eq(&hmac, &signature) {
+            return Ok(());
+        }
+    }
+    Err(SignedPayloadError::NoMatchingSecret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &[u8] = b"Hello, World!";
+    // Examples from https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries
+    const SHA256: &str = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    fn secrets(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn sha256_signature() {
+        let secrets = secrets(&["It's a Secret to Everybody"]);
+        assert_eq!(
+            assert_signed(SignatureAlgorithm::Sha256, SHA256, PAYLOAD, &secrets),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rotated_secrets() {
+        let secrets = secrets(&["old secret", "It's a Secret to Everybody"]);
+        assert_eq!(
+            assert_signed(SignatureAlgorithm::Sha256, SHA256, PAYLOAD, &secrets),
+            Ok(())
+        );
+        let secrets = secrets[..1].to_vec();
+        assert_eq!(
+            assert_signed(SignatureAlgorithm::Sha256, SHA256, PAYLOAD, &secrets),
+            Err(SignedPayloadError::NoMatchingSecret)
+        );
+    }
+
+    #[test]
+    fn rejected_signatures() {
+        let secrets = secrets(&["It's a Secret to Everybody"]);
+        assert_eq!(
+            assert_signed(SignatureAlgorithm::Sha1, SHA256, PAYLOAD, &secrets),
+            Err(SignedPayloadError::WrongAlgorithm)
+        );
+        assert_eq!(
+            assert_signed(SignatureAlgorithm::Sha256, "sha256=xyz", PAYLOAD, &secrets),
+            Err(SignedPayloadError::MalformedHex)
+        );
+        assert_eq!(
+            assert_signed(SignatureAlgorithm::Sha256, "sha256=abcd", PAYLOAD, &secrets),
+            Err(SignedPayloadError::NoMatchingSecret)
+        );
+    }
 
-    if !memcmp::eq(&hmac, &signature) {
-        return Err(SignedPayloadError);
+    #[test]
+    fn enabled_values() {
+        assert!(is_enabled("1"));
+        assert!(is_enabled("true"));
+        assert!(is_enabled("TRUE"));
+        assert!(!is_enabled("0"));
+        assert!(!is_enabled("false"));
+        assert!(!is_enabled(""));
     }
-    Ok(())
 }
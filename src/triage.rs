@@ -22,7 +22,7 @@ pub async fn pulls(
     owner: &str,
     repo: &str,
 ) -> Result<Response<Body>, hyper::Error> {
-    let octocrab = &ctx.octocrab;
+    let octocrab = ctx.octocrab_for(owner).await;
     let res = octocrab
         .pulls(owner, repo)
         .list()
@@ -1,5 +1,6 @@
 use crate::db::notifications::add_metadata;
 use crate::db::notifications::{self, delete_ping, move_indices, record_ping, Identifier};
+use crate::db::vacations;
 use crate::github::{self, GithubClient};
 use crate::handlers::docs_update::docs_update;
 use crate::handlers::Context;
@@ -76,6 +77,19 @@ pub async fn to_github_id(client: &GithubClient, zulip_id: usize) -> anyhow::Res
     Ok(map.users.get(&zulip_id).map(|v| *v as i64))
 }
 
+/// Maps a GitHub ID to the username, for members of a rust-lang team.
+async fn to_github_username(
+    client: &GithubClient,
+    github_id: i64,
+) -> anyhow::Result<Option<String>> {
+    let teams = crate::team_data::teams(client).await?;
+    Ok(teams.teams["all"]
+        .members
+        .iter()
+        .find(|member| member.github_id as i64 == github_id)
+        .map(|member| member.github.clone()))
+}
+
 pub async fn to_zulip_id(client: &GithubClient, github_id: i64) -> anyhow::Result<Option<usize>> {
     let map = crate::team_data::zulip_map(client).await?;
     Ok(map
@@ -156,6 +170,8 @@ fn handle_command<'a>(
                 .map_err(|e| format_err!("Failed to parse movement, expected `move <from> <to>`: {e:?}.")),
             Some("meta") => add_meta_notification(&ctx, gh_id, words).await
                 .map_err(|e| format_err!("Failed to parse movement, expected `move <idx> <meta...>`: {e:?}.")),
+            Some("vacation") => vacation(&ctx, gh_id, words).await
+                .map_err(|e| format_err!("Failed to parse vacation, expected `vacation [until <yyyy-mm-dd> | end]`: {e:?}.")),
             _ => {
                 while let Some(word) = next {
                     if word == "@**triagebot**" {
@@ -239,10 +255,12 @@ async fn execute_for_other_user(
 
     let members = ctx
         .github
-        .raw()
-        .get("https://rust-lang.zulipchat.com/api/v1/users")
-        .basic_auth(BOT_EMAIL, Some(&bot_api_token))
-        .send()
+        .send_raw(
+            ctx.github
+                .raw()
+                .get("https://rust-lang.zulipchat.com/api/v1/users")
+                .basic_auth(BOT_EMAIL, Some(&bot_api_token)),
+        )
         .await
         .map_err(|e| format_err!("Failed to get list of zulip users: {e:?}."))?;
     let members = members
@@ -278,7 +296,7 @@ async fn execute_for_other_user(
         },
         content: &message,
     }
-    .send(ctx.github.raw())
+    .send(&ctx.github)
     .await;
 
     match res {
@@ -397,7 +415,7 @@ impl<'a> MessageApiRequest<'a> {
         self.recipient.url()
     }
 
-    pub async fn send(&self, client: &reqwest::Client) -> anyhow::Result<reqwest::Response> {
+    pub async fn send(&self, client: &GithubClient) -> anyhow::Result<reqwest::Response> {
         let bot_api_token = env::var("ZULIP_API_TOKEN").expect("ZULIP_API_TOKEN");
 
         #[derive(serde::Serialize)]
@@ -410,7 +428,8 @@ impl<'a> MessageApiRequest<'a> {
             content: &'a str,
         }
 
-        Ok(client
+        let req = client
+            .raw()
             .post("https://rust-lang.zulipchat.com/api/v1/messages")
             .basic_auth(BOT_EMAIL, Some(&bot_api_token))
             .form(&SerializedApi {
@@ -427,9 +446,8 @@ impl<'a> MessageApiRequest<'a> {
                     Recipient::Private { .. } => None,
                 },
                 content: self.content,
-            })
-            .send()
-            .await?)
+            });
+        Ok(client.send_raw(req).await?)
     }
 }
 
@@ -448,7 +466,7 @@ pub struct UpdateMessageApiRequest<'a> {
 }
 
 impl<'a> UpdateMessageApiRequest<'a> {
-    pub async fn send(&self, client: &reqwest::Client) -> anyhow::Result<reqwest::Response> {
+    pub async fn send(&self, client: &GithubClient) -> anyhow::Result<reqwest::Response> {
         let bot_api_token = env::var("ZULIP_API_TOKEN").expect("ZULIP_API_TOKEN");
 
         #[derive(serde::Serialize)]
@@ -461,7 +479,8 @@ impl<'a> UpdateMessageApiRequest<'a> {
             pub content: Option<&'a str>,
         }
 
-        Ok(client
+        let req = client
+            .raw()
             .patch(&format!(
                 "https://rust-lang.zulipchat.com/api/v1/messages/{}",
                 self.message_id
@@ -471,9 +490,8 @@ impl<'a> UpdateMessageApiRequest<'a> {
                 topic: self.topic,
                 propagate_mode: self.propagate_mode,
                 content: self.content,
-            })
-            .send()
-            .await?)
+            });
+        Ok(client.send_raw(req).await?)
     }
 }
 
@@ -634,6 +652,40 @@ async fn move_notification(
     }
 }
 
+/// Shows, starts or ends the vacation of the user, like `@rustbot vacation` on
+/// GitHub.
+async fn vacation(
+    ctx: &Context,
+    gh_id: i64,
+    mut words: impl Iterator<Item = &str>,
+) -> anyhow::Result<Option<String>> {
+    let username = match to_github_username(&ctx.github, gh_id).await? {
+        Some(username) => username,
+        None => anyhow::bail!("only members of a rust-lang team can set a vacation"),
+    };
+    let db = ctx.db.get().await;
+    let resp = match (words.next(), words.next(), words.next()) {
+        (None, None, None) => match vacations::vacation_until(&db, &username).await? {
+            Some(until) => format!("You are on vacation until {until}."),
+            None => "You are not on vacation.".to_string(),
+        },
+        (Some("until"), Some(until), None) => {
+            let until = vacations::parse_until(until).map_err(|e| format_err!("{e}"))?;
+            vacations::start_vacation(&db, &username, until).await?;
+            format!("You are on vacation until {until}, and won't be assigned PRs until then.")
+        }
+        (Some("end"), None, None) => {
+            if vacations::end_vacation(&db, &username).await? {
+                "Welcome back! You can be assigned PRs again.".to_string()
+            } else {
+                "You are not on vacation.".to_string()
+            }
+        }
+        _ => anyhow::bail!("unexpected arguments"),
+    };
+    Ok(Some(resp))
+}
+
 #[derive(serde::Serialize, Debug)]
 struct ResponseNotRequired {
     response_not_required: bool,
@@ -651,18 +703,18 @@ struct AddReaction<'a> {
 }
 
 impl<'a> AddReaction<'a> {
-    pub async fn send(self, client: &reqwest::Client) -> anyhow::Result<reqwest::Response> {
+    pub async fn send(self, client: &GithubClient) -> anyhow::Result<reqwest::Response> {
         let bot_api_token = env::var("ZULIP_API_TOKEN").expect("ZULIP_API_TOKEN");
 
-        Ok(client
+        let req = client
+            .raw()
             .post(&format!(
                 "https://rust-lang.zulipchat.com/api/v1/messages/{}/reactions",
                 self.message_id
             ))
             .basic_auth(BOT_EMAIL, Some(&bot_api_token))
-            .form(&self)
-            .send()
-            .await?)
+            .form(&self);
+        Ok(client.send_raw(req).await?)
     }
 }
 
@@ -715,7 +767,7 @@ async fn post_waiter(
         },
         content: waiting.primary,
     }
-    .send(ctx.github.raw())
+    .send(&ctx.github)
     .await?;
     let body = posted.text().await?;
     let message_id = serde_json::from_str::<SentMessage>(&body)
@@ -727,7 +779,7 @@ async fn post_waiter(
             message_id,
             emoji_name: reaction,
         }
-        .send(&ctx.github.raw())
+        .send(&ctx.github)
         .await
         .context("emoji reaction failed")?;
     }
@@ -754,7 +806,8 @@ fn trigger_docs_update(message: &Message) -> anyhow::Result<Option<String>> {
             recipient,
             content: &response,
         };
-        if let Err(e) = message.send(&reqwest::Client::new()).await {
+        let client = GithubClient::new_with_default_token(reqwest::Client::new());
+        if let Err(e) = message.send(&client).await {
             log::error!("failed to send Zulip response: {e:?}\nresponse was:\n{response}");
         }
     });